@@ -0,0 +1,28 @@
+use crate::AssError;
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Access control for files and images stored on smooth storage
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Acl {
+    /// Anyone with the url can access the resource
+    #[default]
+    #[display(fmt = "public")]
+    Public,
+    /// The resource can only be accessed through signed urls
+    #[display(fmt = "private")]
+    Private,
+}
+
+impl FromStr for Acl {
+    type Err = AssError;
+    fn from_str(s: &str) -> Result<Acl, AssError> {
+        match s {
+            "public" => Ok(Acl::Public),
+            "private" => Ok(Acl::Private),
+            _ => Err(AssError::invalid_acl(s.to_string())),
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use crate::AssError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Characters allowed unencoded in a url path segment, see RFC 3986 `pchar`
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+///
+/// A validated path to a file or directory in smooth storage.
+///
+/// Leading, trailing and repeated slashes are removed, so `"reports"`, `"/reports/"` and
+/// `"reports//"` all refer to the same directory. Paths containing `.` or `..` segments, `?`,
+/// `#`, `\` or control characters are rejected. Segments are percent-encoded when the path is
+/// used in a url, so paths must be given decoded: a literal `%` is encoded as `%25`.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::AssPath;
+///
+/// let path = AssPath::parse("/reports//2020/")?.join("annual report.pdf")?;
+/// assert_eq!(path.to_string(), "reports/2020/annual report.pdf");
+/// assert_eq!(path.file_name(), Some("annual report.pdf"));
+///
+/// assert!(AssPath::parse("reports/../secret").is_err());
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct AssPath {
+    path: String,
+}
+
+impl AssPath {
+    /// Parse and normalize a path
+    pub fn parse(path: &str) -> Result<Self, AssError> {
+        let mut segments = vec![];
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            validate_segment(path, segment)?;
+            segments.push(segment);
+        }
+        Ok(AssPath {
+            path: segments.join("/"),
+        })
+    }
+
+    /// The root directory
+    pub fn root() -> Self {
+        Default::default()
+    }
+
+    /// Create a new path by appending a relative path to this one
+    pub fn join(&self, path: &str) -> Result<Self, AssError> {
+        let path = AssPath::parse(path)?;
+        if self.is_root() {
+            Ok(path)
+        } else if path.is_root() {
+            Ok(self.clone())
+        } else {
+            Ok(AssPath {
+                path: format!("{}/{}", self.path, path.path),
+            })
+        }
+    }
+
+    /// Check whether this is the root directory
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    /// The segments of the path
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.path.split('/').filter(|s| !s.is_empty())
+    }
+
+    /// The last segment of the path
+    pub fn file_name(&self) -> Option<&str> {
+        self.segments().last()
+    }
+
+    /// The path without its last segment, `None` for the root directory
+    pub fn parent(&self) -> Option<Self> {
+        if self.is_root() {
+            return None;
+        }
+        let path = match self.path.rfind('/') {
+            Some(i) => self.path[..i].to_string(),
+            None => String::new(),
+        };
+        Some(AssPath { path })
+    }
+
+    /// The path with each segment percent-encoded, for use in urls
+    pub fn to_url_path(&self) -> String {
+        self.segments()
+            .map(|s| utf8_percent_encode(s, PATH_SEGMENT).to_string())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+fn validate_segment(path: &str, segment: &str) -> Result<(), AssError> {
+    if segment == "." || segment == ".." {
+        return Err(AssError::invalid_path(
+            path.to_string(),
+            "relative segments are not allowed".to_string(),
+        ));
+    }
+    if let Some(c) = segment
+        .chars()
+        .find(|c| c.is_control() || *c == '?' || *c == '#' || *c == '\\')
+    {
+        return Err(AssError::invalid_path(
+            path.to_string(),
+            format!("invalid character {:?}", c),
+        ));
+    }
+    Ok(())
+}
+
+impl FromStr for AssPath {
+    type Err = AssError;
+    fn from_str(s: &str) -> Result<AssPath, AssError> {
+        AssPath::parse(s)
+    }
+}
+
+impl AsRef<str> for AssPath {
+    fn as_ref(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Display for AssPath {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "{}", self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AssErrorKind, AssPath};
+
+    #[test]
+    fn normalize_path() {
+        for path in ["reports", "reports/", "/reports", "//reports//"].iter() {
+            let path = AssPath::parse(path).expect("Could not parse path");
+            assert_eq!(path.as_ref(), "reports");
+        }
+        assert!(AssPath::parse("/").expect("Could not parse path").is_root());
+    }
+
+    #[test]
+    fn join_path() {
+        let path = AssPath::parse("reports")
+            .and_then(|p| p.join("2020/q1.pdf"))
+            .expect("Could not join path");
+        assert_eq!(path.as_ref(), "reports/2020/q1.pdf");
+        assert_eq!(path.file_name(), Some("q1.pdf"));
+        assert_eq!(
+            path.parent().map(|p| p.to_string()),
+            Some("reports/2020".to_string())
+        );
+        assert_eq!(
+            AssPath::root().join("q1.pdf").map(|p| p.to_string()).ok(),
+            Some("q1.pdf".to_string())
+        );
+    }
+
+    #[test]
+    fn encode_path() {
+        let path = AssPath::parse("rapporter/årsrapport 100%.pdf").expect("Could not parse path");
+        assert_eq!(path.to_url_path(), "rapporter/%C3%A5rsrapport%20100%25.pdf");
+
+        let path = AssPath::parse("my%20report.pdf").expect("Could not parse path");
+        assert_eq!(path.to_url_path(), "my%2520report.pdf");
+    }
+
+    #[test]
+    fn reject_invalid_paths() {
+        for path in [
+            "../secret",
+            "reports/./q1.pdf",
+            "q1.pdf?x=1",
+            "a#b",
+            "a\\b",
+            "a\nb",
+        ]
+        .iter()
+        {
+            match AssPath::parse(path) {
+                Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidPath { .. })),
+                _ => panic!("Parsing {} should fail", path),
+            }
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::AssError;
+use percent_encoding::percent_decode_str;
+use reqwest::Url;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const ACCESS_TOKEN_PARAM: &str = "accessToken";
+
+/// The resource an `AssUrl` points to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssResource {
+    /// An image, referenced by id
+    Image { id: u64, extension: Option<String> },
+    /// A file, referenced by its path
+    File { path: String },
+}
+
+///
+/// A Smooth Storage url parsed into its parts.
+///
+/// Parses urls as produced by `get_file_url`, `get_image_url` or found in
+/// `FileData::original_url`, and formats back to the same url using `Display`. The account,
+/// path and extension accessors return percent-decoded values, while `Display` keeps the
+/// encoding of the parsed url, so signed urls still verify.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{AssResource, AssUrl};
+///
+/// let url: AssUrl = "https://storage.url.com/users/username/images/5.jpg?t[resize][width]=300".parse()?;
+/// assert_eq!(url.account(), "username");
+/// assert_eq!(url.id(), Some(5));
+/// assert_eq!(url.extension(), Some("jpg"));
+/// assert_eq!(url.params(), vec![("t[resize][width]".to_string(), "300".to_string())]);
+/// assert_eq!(url.to_string(), "https://storage.url.com/users/username/images/5.jpg?t[resize][width]=300");
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssUrl {
+    base: Url,
+    /// The path from the `users` segment on, encoded as in the parsed url
+    encoded_path: String,
+    account: String,
+    resource: AssResource,
+    query: Option<String>,
+    access_token: Option<String>,
+}
+
+impl AssUrl {
+    /// Parse a Smooth Storage url
+    pub fn parse(url: &str) -> Result<Self, AssError> {
+        let invalid = || AssError::invalid_ass_url(url.to_string());
+        let parsed = Url::parse(url)?;
+        let segments: Vec<&str> = parsed.path_segments().ok_or_else(invalid)?.collect();
+        let users = segments
+            .iter()
+            .position(|s| *s == "users")
+            .ok_or_else(invalid)?;
+        let (account, kind, rest) = match &segments[users + 1..] {
+            [account, kind, rest @ ..] if !account.is_empty() && !rest.is_empty() => {
+                (decode(account), *kind, rest)
+            }
+            _ => return Err(invalid()),
+        };
+
+        let resource = match (kind, rest) {
+            ("images", [image]) => {
+                let (id, extension) = match image.find('.') {
+                    Some(i) => (&image[..i], Some(decode(&image[i + 1..]))),
+                    None => (*image, None),
+                };
+                AssResource::Image {
+                    id: id.parse().map_err(|_| invalid())?,
+                    extension,
+                }
+            }
+            ("files", path) if path.iter().all(|s| !s.is_empty()) => AssResource::File {
+                path: path.iter().map(|s| decode(s)).collect::<Vec<_>>().join("/"),
+            },
+            _ => return Err(invalid()),
+        };
+
+        let mut base = parsed.clone();
+        base.set_path(&format!("{}/", segments[..users].join("/")));
+        base.set_query(None);
+        base.set_fragment(None);
+
+        let mut access_token = None;
+        let mut query = vec![];
+        for pair in parsed.query().unwrap_or("").split('&') {
+            if pair.starts_with(&format!("{}=", ACCESS_TOKEN_PARAM)) {
+                access_token = Some(pair[ACCESS_TOKEN_PARAM.len() + 1..].to_string());
+            } else if !pair.is_empty() {
+                query.push(pair);
+            }
+        }
+
+        Ok(AssUrl {
+            base,
+            encoded_path: segments[users..].join("/"),
+            account,
+            resource,
+            query: if query.is_empty() {
+                None
+            } else {
+                Some(query.join("&"))
+            },
+            access_token,
+        })
+    }
+
+    /// Find all Smooth Storage urls in a piece of text, such as an HTML document
+    pub fn find_in(text: &str) -> Vec<AssUrl> {
+        let text = text.replace("&amp;", "&");
+        let mut urls = vec![];
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find("http") {
+            let candidate = &rest[start..];
+            let end = candidate
+                .find(|c: char| c.is_whitespace() || "\"'<>()".contains(c))
+                .unwrap_or(candidate.len());
+            if let Ok(url) = AssUrl::parse(&candidate[..end]) {
+                urls.push(url);
+            }
+            rest = &candidate[end..];
+        }
+        urls
+    }
+
+    /// The storage base url, up to the `users/` segment
+    pub fn base(&self) -> &Url {
+        &self.base
+    }
+
+    /// Name of the account owning the resource
+    pub fn account(&self) -> &str {
+        &self.account
+    }
+
+    /// The resource the url points to
+    pub fn resource(&self) -> &AssResource {
+        &self.resource
+    }
+
+    /// Image id, if the url points to an image
+    pub fn id(&self) -> Option<u64> {
+        match &self.resource {
+            AssResource::Image { id, .. } => Some(*id),
+            AssResource::File { .. } => None,
+        }
+    }
+
+    /// File path, if the url points to a file
+    pub fn path(&self) -> Option<&str> {
+        match &self.resource {
+            AssResource::Image { .. } => None,
+            AssResource::File { path } => Some(path),
+        }
+    }
+
+    /// Extension of the image or file name
+    pub fn extension(&self) -> Option<&str> {
+        match &self.resource {
+            AssResource::Image { extension, .. } => extension.as_deref(),
+            AssResource::File { path } => {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                name.rfind('.').map(|i| &name[i + 1..])
+            }
+        }
+    }
+
+    /// Transformation parameters from the query string, excluding the access token
+    pub fn params(&self) -> Vec<(String, String)> {
+        match &self.query {
+            Some(query) => url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The url's access token, if it is signed
+    pub fn access_token(&self) -> Option<&str> {
+        self.access_token.as_deref()
+    }
+}
+
+impl FromStr for AssUrl {
+    type Err = AssError;
+    fn from_str(s: &str) -> Result<AssUrl, AssError> {
+        AssUrl::parse(s)
+    }
+}
+
+impl Display for AssUrl {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "{}{}", self.base, self.encoded_path)?;
+        let query = self
+            .query
+            .iter()
+            .cloned()
+            .chain(
+                self.access_token
+                    .iter()
+                    .map(|token| format!("{}={}", ACCESS_TOKEN_PARAM, token)),
+            )
+            .collect::<Vec<_>>();
+        if !query.is_empty() {
+            write!(fmt, "?{}", query.join("&"))?;
+        }
+        Ok(())
+    }
+}
+
+fn decode(s: &str) -> String {
+    percent_decode_str(s).decode_utf8_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{file_handling, image_handling, AssClient, AssErrorKind, AssResource, AssUrl};
+
+    #[test]
+    fn parse_image_url() {
+        let ass_client = AssClient::create("http://url/base/", "name", "apikey")
+            .expect("Could not get AssClient");
+        let url = image_handling::get_image_url(&ass_client, 42).expect("Could not get url");
+
+        let ass_url: AssUrl = url.parse().expect("Could not parse url");
+        assert_eq!(ass_url.base().as_str(), "http://url/base/");
+        assert_eq!(ass_url.account(), "name");
+        assert_eq!(
+            ass_url.resource(),
+            &AssResource::Image {
+                id: 42,
+                extension: Some("jpg".to_string())
+            }
+        );
+        assert!(ass_url.access_token().is_some());
+        assert_eq!(ass_url.to_string(), url);
+    }
+
+    #[test]
+    fn parse_file_url() {
+        let ass_client =
+            AssClient::create("http://url", "name", "apikey").expect("Could not get AssClient");
+        let url = file_handling::get_file_url(&ass_client, "reports/2020/my report.pdf")
+            .expect("Could not get url");
+
+        let ass_url: AssUrl = url.parse().expect("Could not parse url");
+        assert_eq!(ass_url.path(), Some("reports/2020/my report.pdf"));
+        assert_eq!(ass_url.extension(), Some("pdf"));
+        assert_eq!(ass_url.id(), None);
+        assert_eq!(ass_url.to_string(), url);
+    }
+
+    #[test]
+    fn file_url_with_reserved_characters_round_trips() {
+        let ass_client =
+            AssClient::create("http://url", "name", "apikey").expect("Could not get AssClient");
+        for path in ["docs/a[1].pdf", "docs/a^b|c.pdf", "docs/100%.pdf"].iter() {
+            let url = file_handling::get_file_url(&ass_client, path).expect("Could not get url");
+
+            let ass_url: AssUrl = url.parse().expect("Could not parse url");
+            assert_eq!(ass_url.path(), Some(*path));
+            assert_eq!(ass_url.to_string(), url);
+            ass_client
+                .verify_signed_url(&ass_url.to_string())
+                .expect("Could not verify formatted url");
+        }
+
+        let url = "http://url/users/name/files/a%2Fb.pdf";
+        let ass_url: AssUrl = url.parse().expect("Could not parse url");
+        assert_eq!(ass_url.path(), Some("a/b.pdf"));
+        assert_eq!(ass_url.to_string(), url);
+    }
+
+    #[test]
+    fn parse_invalid_url() {
+        let urls = [
+            "http://url/images/2.jpg",
+            "http://url/users/name/images/foo.jpg",
+            "http://url/users/name/videos/2",
+            "http://url/users/name/files/",
+        ];
+        for url in urls.iter() {
+            match url.parse::<AssUrl>() {
+                Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidAssUrl(_))),
+                _ => panic!("Parsing {} should fail", url),
+            }
+        }
+    }
+
+    #[test]
+    fn find_urls_in_html() {
+        let html = r#"<p><img src="http://url/users/name/images/12.png?t[resize][width]=300&amp;t[crop]=1">
+            <a href='https://url/users/name/files/doc.pdf'>doc</a> http://example.com/foo</p>"#;
+
+        let urls = AssUrl::find_in(html);
+        assert_eq!(urls.len(), 2);
+        assert_eq!(urls[0].id(), Some(12));
+        assert_eq!(
+            urls[0].params(),
+            vec![
+                ("t[resize][width]".to_string(), "300".to_string()),
+                ("t[crop]".to_string(), "1".to_string())
+            ]
+        );
+        assert_eq!(urls[1].path(), Some("doc.pdf"));
+    }
+}
@@ -0,0 +1,458 @@
+//!
+//! `ass`, a command-line tool for everyday smooth storage tasks.
+//!
+//! Credentials are read from the account file given with `--account` or the `ASS_ACCOUNT_FILE`
+//! environment variable, from the profile given with `--profile` or the default profile in the
+//! profiles file, or from the `ASS_URL`, `ASS_NAME` and `ASS_APIKEY` environment variables.
+//!
+
+use ass_rs::blocking::{file_handling, image_handling};
+use ass_rs::{Acl, AssClient, AssError, FileData, ImageData, Profiles, UploadOptions};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: ass [options] <command> [arguments]
+
+Commands:
+    upload file <path> <destination>     Upload a file to a destination directory
+    upload image <path>                  Upload an image
+    upload dir <directory> <destination> Upload all files in a directory, recursively
+    download file <path>                 Download a file
+    download image <id>                  Download an image
+    info file <id or path>               Show file information
+    info image <id>                      Show image information
+    search [<key>=<value> ...]           Search for files, such as `path=reports/`
+    sign file <path>                     Print a signed url for a file
+    sign image <id>                      Print a signed url for an image
+    rendition <file id>                  Show the default image rendition of a file
+    delete file <id>                     Delete a file
+    delete image <id>                    Delete an image
+
+Options:
+    --account <file>    Account file with url, name and apikey
+    --profile <name>    Profile to use from the profiles file
+    --json              Print results as JSON
+    --acl <acl>         Acl for uploads, `public` or `private`
+    --expires <secs>    Seconds until signed urls expire
+    -o, --output <file> Where to write downloads, defaults to the file or image name
+    -h, --help          Show this help";
+
+#[derive(Default)]
+struct Args {
+    account: Option<PathBuf>,
+    profile: Option<String>,
+    json: bool,
+    acl: Option<Acl>,
+    expires: Option<u32>,
+    output: Option<PathBuf>,
+    positional: Vec<String>,
+}
+
+enum CliError {
+    Usage(String),
+    Ass(AssError),
+}
+
+impl From<AssError> for CliError {
+    fn from(err: AssError) -> Self {
+        CliError::Ass(err)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(err: std::io::Error) -> Self {
+        CliError::Ass(err.into())
+    }
+}
+
+fn main() {
+    let result = parse_args(std::env::args().skip(1)).and_then(|args| run(&args));
+    match result {
+        Ok(()) => {}
+        Err(CliError::Usage(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(CliError::Ass(err)) => {
+            eprintln!("error: {}", err);
+            let mut source = std::error::Error::source(&err);
+            while let Some(err) = source {
+                eprintln!("  caused by: {}", err);
+                source = err.source();
+            }
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut raw: I) -> Result<Args, CliError> {
+    let mut args = Args::default();
+    while let Some(arg) = raw.next() {
+        let mut value = |name: &str| {
+            raw.next()
+                .ok_or_else(|| CliError::Usage(format!("{} needs a value", name)))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "--json" => args.json = true,
+            "--account" => args.account = Some(value("--account")?.into()),
+            "--profile" => args.profile = Some(value("--profile")?),
+            "-o" | "--output" => args.output = Some(value("--output")?.into()),
+            "--acl" => {
+                let acl = value("--acl")?;
+                args.acl = Some(
+                    acl.parse()
+                        .map_err(|_| CliError::Usage(format!("Invalid acl `{}`", acl)))?,
+                );
+            }
+            "--expires" => {
+                let expires = value("--expires")?;
+                args.expires = Some(expires.parse().map_err(|_| {
+                    CliError::Usage(format!(
+                        "Invalid number of seconds `{}`, expected 0 to {}",
+                        expires,
+                        u32::MAX
+                    ))
+                })?);
+            }
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(CliError::Usage(format!("Unknown option `{}`", flag)));
+            }
+            _ => args.positional.push(arg),
+        }
+    }
+    Ok(args)
+}
+
+fn client(args: &Args) -> Result<AssClient, CliError> {
+    let account_file = args
+        .account
+        .clone()
+        .or_else(|| std::env::var_os("ASS_ACCOUNT_FILE").map(PathBuf::from));
+    if let Some(account_file) = account_file {
+        return Ok(AssClient::from_file(account_file)?);
+    }
+    if let Some(profile) = &args.profile {
+        return Ok(AssClient::from_profile(profile)?);
+    }
+    if Profiles::config_path().is_some() {
+        return Ok(AssClient::from_default_profile()?);
+    }
+    Ok(AssClient::from_env()?)
+}
+
+fn run(args: &Args) -> Result<(), CliError> {
+    let positional: Vec<&str> = args.positional.iter().map(|s| s.as_str()).collect();
+    if positional.is_empty() {
+        return Err(CliError::Usage("Missing command".to_string()));
+    }
+    let ass_client = client(args)?;
+    let mut options = UploadOptions::new();
+    if let Some(acl) = args.acl {
+        options = options.acl(acl);
+    }
+
+    match positional.as_slice() {
+        ["upload", "file", path, destination] => {
+            let file = file_handling::upload_file_with_options(
+                &ass_client,
+                *path,
+                *destination,
+                &options,
+            )?;
+            print_files(args, &[file])
+        }
+        ["upload", "image", path] => {
+            let image = image_handling::upload_image_with_options(&ass_client, *path, &options)?;
+            print_images(args, &[image])
+        }
+        ["upload", "dir", directory, destination] => {
+            let mut files = vec![];
+            upload_directory(
+                &ass_client,
+                Path::new(directory),
+                destination,
+                &options,
+                &mut files,
+            )?;
+            print_files(args, &files)
+        }
+        ["download", "file", path] => {
+            let content = file_handling::download_file(&ass_client, *path)?;
+            write_download(args, path, &content)
+        }
+        ["download", "image", id] => {
+            let id = parse_id(id)?;
+            let image = image_handling::get_image_information(&ass_client, id)?;
+            let content = image_handling::download_image(&ass_client, id)?;
+            write_download(args, &image.name, &content)
+        }
+        ["info", "file", id_or_path] => {
+            let file = match id_or_path.parse::<u64>() {
+                Ok(id) => file_handling::get_file_information_by_id(&ass_client, id)?,
+                Err(_) => file_handling::get_file_information_by_path(&ass_client, *id_or_path)?,
+            };
+            print_files(args, &[file])
+        }
+        ["info", "image", id] => {
+            let image = image_handling::get_image_information(&ass_client, parse_id(id)?)?;
+            print_images(args, &[image])
+        }
+        ["search", queries @ ..] => {
+            let mut pairs = vec![];
+            for query in queries {
+                let i = query.find('=').ok_or_else(|| {
+                    CliError::Usage(format!("Search queries must be key=value, got `{}`", query))
+                })?;
+                pairs.push((&query[..i], &query[i + 1..]));
+            }
+            let files = file_handling::search(&ass_client, &pairs)?;
+            print_files(args, &files)
+        }
+        ["sign", "file", path] => {
+            let url = match expiry(args)? {
+                Some(expires) => file_handling::get_file_url_expiring(&ass_client, *path, expires)?,
+                None => file_handling::get_file_url(&ass_client, *path)?,
+            };
+            print_url(args, &url)
+        }
+        ["sign", "image", id] => {
+            let id = parse_id(id)?;
+            let url = match expiry(args)? {
+                Some(expires) => image_handling::get_image_url_expiring(&ass_client, id, expires)?,
+                None => image_handling::get_image_url(&ass_client, id)?,
+            };
+            print_url(args, &url)
+        }
+        ["rendition", id] => {
+            let image = file_handling::get_file_rendition(&ass_client, parse_id(id)?)?;
+            print_images(args, &[image])
+        }
+        ["delete", "file", id] => Ok(file_handling::delete_file(&ass_client, parse_id(id)?)?),
+        ["delete", "image", id] => Ok(image_handling::delete_image(&ass_client, parse_id(id)?)?),
+        _ => Err(CliError::Usage(format!(
+            "Unknown command `{}`",
+            positional.join(" ")
+        ))),
+    }
+}
+
+fn parse_id(id: &str) -> Result<u64, CliError> {
+    id.parse()
+        .map_err(|_| CliError::Usage(format!("Invalid id `{}`", id)))
+}
+
+/// Upload the files in `directory` and its subdirectories, keeping the directory structure
+/// below `destination`
+fn upload_directory(
+    ass_client: &AssClient,
+    directory: &Path,
+    destination: &str,
+    options: &UploadOptions,
+    files: &mut Vec<FileData>,
+) -> Result<(), CliError> {
+    let mut entries = std::fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            let name = entry.file_name();
+            let destination = format!("{}/{}", destination, name.to_string_lossy());
+            upload_directory(ass_client, &path, &destination, options, files)?;
+        } else {
+            let file =
+                file_handling::upload_file_with_options(ass_client, path, destination, options)?;
+            files.push(file);
+        }
+    }
+    Ok(())
+}
+
+fn write_download(args: &Args, name: &str, content: &[u8]) -> Result<(), CliError> {
+    let output = match &args.output {
+        Some(output) => output.clone(),
+        None => download_path(name)?,
+    };
+    std::fs::write(&output, content)?;
+    if args.json {
+        print_json(&serde_json::json!({
+            "path": output.to_string_lossy(),
+            "bytes": content.len(),
+        }))
+    } else {
+        println!("{} bytes written to {}", content.len(), output.display());
+        Ok(())
+    }
+}
+
+/// The expiry time for signed urls given with `--expires`
+fn expiry(args: &Args) -> Result<Option<DateTime<Utc>>, CliError> {
+    match args.expires {
+        Some(seconds) => Utc::now()
+            .checked_add_signed(Duration::seconds(i64::from(seconds)))
+            .map(Some)
+            .ok_or_else(|| CliError::Usage(format!("Expiry `{}` is too far away", seconds))),
+        None => Ok(None),
+    }
+}
+
+/// The file name part of a remote name, so downloads are always written to the working
+/// directory
+fn download_path(name: &str) -> Result<PathBuf, AssError> {
+    Path::new(name)
+        .file_name()
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            AssError::invalid_file_name(
+                "No file name to save the download as, use --output".to_string(),
+                name.to_string(),
+            )
+        })
+}
+
+fn print_url(args: &Args, url: &str) -> Result<(), CliError> {
+    if args.json {
+        print_json(&serde_json::json!({ "url": url }))
+    } else {
+        println!("{}", url);
+        Ok(())
+    }
+}
+
+fn print_files(args: &Args, files: &[FileData]) -> Result<(), CliError> {
+    if args.json {
+        return print_json(files);
+    }
+    let rows = files
+        .iter()
+        .map(|file| {
+            vec![
+                file.id.to_string(),
+                file.path.clone(),
+                file.content_type.clone(),
+                file.md5.clone(),
+                file.updated.to_rfc3339(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&["ID", "PATH", "CONTENT TYPE", "MD5", "UPDATED"], &rows);
+    Ok(())
+}
+
+fn print_images(args: &Args, images: &[ImageData]) -> Result<(), CliError> {
+    if args.json {
+        return print_json(images);
+    }
+    let rows = images
+        .iter()
+        .map(|image| {
+            vec![
+                image.id.to_string(),
+                image.name.clone(),
+                format!("{}x{}", image.width, image.height),
+                image.md5.clone(),
+                image.updated.to_rfc3339(),
+            ]
+        })
+        .collect::<Vec<_>>();
+    print_table(&["ID", "NAME", "SIZE", "MD5", "UPDATED"], &rows);
+    Ok(())
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(AssError::from)?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Print rows as columns aligned to the widest value
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let format_row = |values: Vec<&str>| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(value, width)| format!("{:width$}", value, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(headers.to_vec()));
+    for row in rows {
+        println!("{}", format_row(row.iter().map(|s| s.as_str()).collect()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{download_path, parse_args, CliError};
+    use ass_rs::Acl;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn parse_options_and_positionals() {
+        let parsed = match parse_args(args(&[
+            "--json", "upload", "--acl", "private", "file", "a.txt", "docs",
+        ])) {
+            Ok(parsed) => parsed,
+            Err(_) => panic!("Could not parse arguments"),
+        };
+        assert!(parsed.json);
+        assert_eq!(parsed.acl, Some(Acl::Private));
+        assert_eq!(parsed.positional, vec!["upload", "file", "a.txt", "docs"]);
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        for invalid in [
+            &["--acl", "secret"][..],
+            &["--expires"],
+            &["--expires", "-5"],
+            &["--expires", "10000000000000"],
+            &["--verbose"],
+        ]
+        .iter()
+        {
+            match parse_args(args(invalid)) {
+                Err(CliError::Usage(_)) => {}
+                _ => panic!("Parsing {:?} should fail", invalid),
+            }
+        }
+    }
+
+    #[test]
+    fn download_path_stays_in_working_directory() {
+        assert_eq!(
+            download_path("reports/q1.pdf").unwrap(),
+            PathBuf::from("q1.pdf")
+        );
+        assert_eq!(
+            download_path("../../.bashrc").unwrap(),
+            PathBuf::from(".bashrc")
+        );
+        assert_eq!(
+            download_path("/etc/passwd").unwrap(),
+            PathBuf::from("passwd")
+        );
+        assert!(download_path("reports/..").is_err());
+        assert!(download_path("").is_err());
+    }
+}
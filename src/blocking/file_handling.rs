@@ -0,0 +1,137 @@
+//!
+//! Blocking functions for working with files in smooth storage
+//!
+
+use super::block_on;
+use crate::{file_handling, Acl, AssClient, AssError, FileData, ImageData, UploadOptions};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+pub use crate::file_handling::{get_file_url, get_file_url_expiring};
+
+/// Search for files
+pub fn search(ass_client: &AssClient, queries: &[(&str, &str)]) -> Result<Vec<FileData>, AssError> {
+    block_on(file_handling::search(ass_client, queries))
+}
+
+/// Search for files with the given metadata values
+pub fn search_by_metadata(
+    ass_client: &AssClient,
+    metadata: &[(&str, &str)],
+) -> Result<Vec<FileData>, AssError> {
+    block_on(file_handling::search_by_metadata(ass_client, metadata))
+}
+
+/// Upload file without any headers
+pub fn upload_file<T: Into<PathBuf>, D: AsRef<str>>(
+    ass_client: &AssClient,
+    path: T,
+    destination: D,
+) -> Result<FileData, AssError> {
+    block_on(file_handling::upload_file(ass_client, path, destination))
+}
+
+/// Upload file with the given acl, overriding the client's default acl
+pub fn upload_file_with_acl<T: Into<PathBuf>, D: AsRef<str>>(
+    ass_client: &AssClient,
+    path: T,
+    destination: D,
+    acl: Acl,
+) -> Result<FileData, AssError> {
+    block_on(file_handling::upload_file_with_acl(
+        ass_client,
+        path,
+        destination,
+        acl,
+    ))
+}
+
+/// Upload file with the given upload options
+pub fn upload_file_with_options<T: Into<PathBuf>, D: AsRef<str>>(
+    ass_client: &AssClient,
+    path: T,
+    destination: D,
+    options: &UploadOptions,
+) -> Result<FileData, AssError> {
+    block_on(file_handling::upload_file_with_options(
+        ass_client,
+        path,
+        destination,
+        options,
+    ))
+}
+
+/// Replace the custom metadata of an existing file
+pub fn update_file_metadata(
+    ass_client: &AssClient,
+    id: u64,
+    metadata: &HashMap<String, String>,
+) -> Result<FileData, AssError> {
+    block_on(file_handling::update_file_metadata(
+        ass_client, id, metadata,
+    ))
+}
+
+/// Change the acl of an existing file
+pub fn set_file_acl(ass_client: &AssClient, id: u64, acl: Acl) -> Result<FileData, AssError> {
+    block_on(file_handling::set_file_acl(ass_client, id, acl))
+}
+
+/// Returns file information, queried by id
+pub fn get_file_information_by_id(ass_client: &AssClient, id: u64) -> Result<FileData, AssError> {
+    block_on(file_handling::get_file_information_by_id(ass_client, id))
+}
+
+/// Returns file information, queried by path
+pub fn get_file_information_by_path<P: AsRef<str>>(
+    ass_client: &AssClient,
+    path: P,
+) -> Result<FileData, AssError> {
+    block_on(file_handling::get_file_information_by_path(
+        ass_client, path,
+    ))
+}
+
+/// Download the content of the file on the given path, through a signed url
+pub fn download_file<P: AsRef<str>>(ass_client: &AssClient, path: P) -> Result<Vec<u8>, AssError> {
+    block_on(file_handling::download_file(ass_client, path))
+}
+
+/// Delete a file
+pub fn delete_file(ass_client: &AssClient, id: u64) -> Result<(), AssError> {
+    block_on(file_handling::delete_file(ass_client, id))
+}
+
+/// Returns a default image rendition of a file
+pub fn get_file_rendition(ass_client: &AssClient, file_id: u64) -> Result<ImageData, AssError> {
+    block_on(file_handling::get_file_rendition(ass_client, file_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{blocking, AssClient};
+    use mockito;
+
+    #[test]
+    fn test_blocking_file_upload() {
+        let _m = mockito::mock("POST", "/files/blocking-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let result = blocking::file_handling::upload_file(
+            &ass_client,
+            "./data/account.json",
+            "blocking-path",
+        )
+        .expect("Could not get result");
+        assert_eq!(result.id, 2);
+    }
+}
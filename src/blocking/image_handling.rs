@@ -0,0 +1,61 @@
+//!
+//! Blocking functions for working with images in smooth storage
+//!
+
+use super::block_on;
+use crate::{image_handling, Acl, AssClient, AssError, ImageData, UploadOptions};
+use std::path::PathBuf;
+
+pub use crate::image_handling::{get_image_url, get_image_url_expiring};
+
+/// Upload an image to smooth storage
+pub fn upload_image<T: Into<PathBuf>>(
+    ass_client: &AssClient,
+    path: T,
+) -> Result<ImageData, AssError> {
+    block_on(image_handling::upload_image(ass_client, path))
+}
+
+/// Upload an image to smooth storage with the given acl, overriding the client's default acl
+pub fn upload_image_with_acl<T: Into<PathBuf>>(
+    ass_client: &AssClient,
+    path: T,
+    acl: Acl,
+) -> Result<ImageData, AssError> {
+    block_on(image_handling::upload_image_with_acl(ass_client, path, acl))
+}
+
+/// Upload an image to smooth storage with the given upload options
+pub fn upload_image_with_options<T: Into<PathBuf>>(
+    ass_client: &AssClient,
+    path: T,
+    options: &UploadOptions,
+) -> Result<ImageData, AssError> {
+    block_on(image_handling::upload_image_with_options(
+        ass_client, path, options,
+    ))
+}
+
+/// Change the acl of an existing image
+pub fn set_image_acl(
+    ass_client: &AssClient,
+    image_id: u64,
+    acl: Acl,
+) -> Result<ImageData, AssError> {
+    block_on(image_handling::set_image_acl(ass_client, image_id, acl))
+}
+
+/// Download the original content of an image, through a signed url
+pub fn download_image(ass_client: &AssClient, image_id: u64) -> Result<Vec<u8>, AssError> {
+    block_on(image_handling::download_image(ass_client, image_id))
+}
+
+/// Delete an image
+pub fn delete_image(ass_client: &AssClient, image_id: u64) -> Result<(), AssError> {
+    block_on(image_handling::delete_image(ass_client, image_id))
+}
+
+/// Fetch image information
+pub fn get_image_information(ass_client: &AssClient, image_id: u64) -> Result<ImageData, AssError> {
+    block_on(image_handling::get_image_information(ass_client, image_id))
+}
@@ -0,0 +1,30 @@
+//!
+//! Blocking versions of the `file_handling` and `image_handling` modules.
+//!
+//! Each call runs the request on its own single threaded runtime and waits for it to finish.
+//! The functions must not be called from within an async runtime.
+//!
+//! ```ignore
+//! use ass_rs::{blocking, AssClient};
+//!
+//! let ass_client = AssClient::create("https://url-to-storage", "account-name", "secretkey")?;
+//!
+//! let image_data = blocking::image_handling::upload_image(&ass_client, "/data/image.jpg")?;
+//! let file_data = blocking::file_handling::upload_file(&ass_client, "/data/file.pdf", "destination")?;
+//! ```
+//!
+
+pub mod file_handling;
+pub mod image_handling;
+
+use crate::AssError;
+use std::future::Future;
+
+/// Run a future to completion on a new single threaded runtime
+fn block_on<F: Future<Output = Result<T, AssError>>, T>(future: F) -> Result<T, AssError> {
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()?;
+    runtime.block_on(future)
+}
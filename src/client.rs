@@ -1,11 +1,32 @@
-use crate::AssError;
-use reqwest::header::HeaderMap;
+use crate::limits::Limits;
+use crate::metrics::MetricsRecorder;
+use crate::middleware::MiddlewareStack;
+use crate::{Acl, AssError, Metrics, Middleware, Profiles, Secret};
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Url;
 use ring::{digest, hmac};
 use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::prelude::*;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+pub(crate) const ACL_HEADER: &str = "x-ass-acl";
+const ACCESS_TOKEN_PARAM: &str = "accessToken";
+const EXPIRES_PARAM: &str = "expires";
+const DEFAULT_ENV_PREFIX: &str = "ASS";
+/// Number of previous api keys kept by `rotate_apikey`
+pub(crate) const MAX_PREVIOUS_APIKEYS: usize = 3;
+
+/// A signed url that has passed verification
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifiedUrl {
+    /// The url with the access token removed
+    pub url: Url,
+    /// Expiry time embedded in the signature, if any
+    pub expires: Option<DateTime<Utc>>,
+}
 
 ///
 /// Client talking to Aptoma Smooth Storage.
@@ -27,11 +48,66 @@ use std::path::PathBuf;
 /// # }
 /// ```
 ///
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "Account", into = "Account")]
 pub struct AssClient {
     url: String,
     pub name: String,
-    pub apikey: String,
+    keys: Arc<RwLock<KeyRing>>,
+    acl: Acl,
+    metrics: Option<MetricsRecorder>,
+    middleware: MiddlewareStack,
+    limits: Limits,
+}
+
+/// The api key used for requests and signing, and previous keys still accepted when verifying
+/// signed urls
+#[derive(Debug)]
+struct KeyRing {
+    primary: Secret,
+    previous: Vec<Secret>,
+}
+
+/// The account file format
+#[derive(Serialize, Deserialize)]
+struct Account {
+    url: String,
+    name: String,
+    apikey: Secret,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    previous_apikeys: Vec<Secret>,
+    #[serde(default)]
+    acl: Acl,
+}
+
+impl From<Account> for AssClient {
+    fn from(account: Account) -> Self {
+        AssClient {
+            url: account.url,
+            name: account.name,
+            keys: Arc::new(RwLock::new(KeyRing {
+                primary: account.apikey,
+                previous: account.previous_apikeys,
+            })),
+            acl: account.acl,
+            metrics: None,
+            middleware: MiddlewareStack::default(),
+            limits: Limits::default(),
+        }
+    }
+}
+
+impl From<AssClient> for Account {
+    fn from(ass_client: AssClient) -> Self {
+        let keys = ass_client.read_keys();
+        Account {
+            url: ass_client.url.clone(),
+            name: ass_client.name.clone(),
+            apikey: keys.primary.clone(),
+            previous_apikeys: keys.previous.clone(),
+            acl: ass_client.acl,
+        }
+    }
 }
 
 impl AssClient {
@@ -46,10 +122,128 @@ impl AssClient {
         Ok(AssClient {
             url,
             name: name.into(),
-            apikey: apikey.into(),
+            keys: Arc::new(RwLock::new(KeyRing {
+                primary: Secret::new(apikey),
+                previous: vec![],
+            })),
+            acl: Acl::default(),
+            metrics: None,
+            middleware: MiddlewareStack::default(),
+            limits: Limits::default(),
         })
     }
 
+    /// Set the default acl used for uploads made with this client
+    pub fn with_acl(mut self, acl: Acl) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    /// Set the default acl used for uploads made with this client
+    pub fn set_acl(&mut self, acl: Acl) {
+        self.acl = acl;
+    }
+
+    /// Fetch the default acl used for uploads made with this client
+    pub fn acl(&self) -> Acl {
+        self.acl
+    }
+
+    /// Set previous api keys, still accepted when verifying signed urls. The returned client
+    /// gets its own keys, which are no longer shared with clones of the original client
+    pub fn with_previous_apikeys<K: Into<Secret>, I: IntoIterator<Item = K>>(
+        mut self,
+        previous: I,
+    ) -> Self {
+        let primary = self.apikey();
+        self.keys = Arc::new(RwLock::new(KeyRing {
+            primary,
+            previous: previous.into_iter().map(Into::into).collect(),
+        }));
+        self
+    }
+
+    /// Fetch the api key, used for requests and for signing urls
+    pub fn apikey(&self) -> Secret {
+        self.read_keys().primary.clone()
+    }
+
+    /// Fetch the previous api keys, still accepted when verifying signed urls
+    pub fn previous_apikeys(&self) -> Vec<Secret> {
+        self.read_keys().previous.clone()
+    }
+
+    /// Make `apikey` the api key, keeping the current key as the most recent previous key.
+    /// Only the three most recent previous keys are kept. Clones of the client
+    /// share keys, so the change applies to all of them
+    pub fn rotate_apikey<K: Into<Secret>>(&self, apikey: K) {
+        let mut keys = self.write_keys();
+        let previous = std::mem::replace(&mut keys.primary, apikey.into());
+        keys.previous.insert(0, previous);
+        keys.previous.truncate(MAX_PREVIOUS_APIKEYS);
+    }
+
+    /// Replace the api key and all previous api keys. Clones of the client share keys, so the
+    /// change applies to all of them
+    pub fn set_apikeys<K: Into<Secret>, P: Into<Secret>, I: IntoIterator<Item = P>>(
+        &self,
+        apikey: K,
+        previous: I,
+    ) {
+        let mut keys = self.write_keys();
+        keys.primary = apikey.into();
+        keys.previous = previous.into_iter().map(Into::into).collect();
+    }
+
+    /// Report measurements of every request made with this client, and its clones, to `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(MetricsRecorder(metrics));
+        self
+    }
+
+    pub(crate) fn metrics(&self) -> Option<&dyn Metrics> {
+        self.metrics.as_ref().map(|recorder| recorder.0.as_ref())
+    }
+
+    /// Run `middleware` around every request made with this client, after any middleware
+    /// already registered
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    pub(crate) fn middleware(&self) -> &MiddlewareStack {
+        &self.middleware
+    }
+
+    /// Allow at most `requests_per_second` requests to start each second. A limit of 0 is
+    /// treated as 1. The limit is shared with clones made from the returned client, but not with
+    /// clones made earlier
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.limits.set_rate(requests_per_second);
+        self
+    }
+
+    /// Allow at most `max` requests in flight at the same time. A limit of 0 is treated as 1.
+    /// The limit is shared with clones made from the returned client, but not with clones made
+    /// earlier
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.limits.set_max_in_flight(max);
+        self
+    }
+
+    pub(crate) fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    fn read_keys(&self) -> std::sync::RwLockReadGuard<'_, KeyRing> {
+        self.keys.read().expect("Api key lock poisoned")
+    }
+
+    fn write_keys(&self) -> std::sync::RwLockWriteGuard<'_, KeyRing> {
+        self.keys.write().expect("Api key lock poisoned")
+    }
+
     /// Create a new client, fetch `url`, `name` and `password` from account file at the given path
     pub fn from_file<T: Into<PathBuf>>(path: T) -> Result<Self, AssError> {
         let path = path.into();
@@ -65,27 +259,92 @@ impl AssClient {
         })
     }
 
-    /// Fetch the client's base url
+    /// Create a new client from the `ASS_URL`, `ASS_NAME` and `ASS_APIKEY` environment
+    /// variables, and the optional `ASS_ACL`
+    pub fn from_env() -> Result<Self, AssError> {
+        AssClient::from_env_with_prefix(DEFAULT_ENV_PREFIX)
+    }
+
+    /// Create a new client from the `{prefix}_URL`, `{prefix}_NAME` and `{prefix}_APIKEY`
+    /// environment variables, and the optional `{prefix}_ACL`
+    pub fn from_env_with_prefix(prefix: &str) -> Result<Self, AssError> {
+        AssClient::from_env_vars(prefix, |var| std::env::var(var))
+    }
+
+    fn from_env_vars<F: Fn(&str) -> Result<String, std::env::VarError>>(
+        prefix: &str,
+        env: F,
+    ) -> Result<Self, AssError> {
+        let var = |suffix: &str| {
+            let var = format!("{}_{}", prefix, suffix);
+            match env(&var) {
+                Ok(value) => Ok(Some(value)),
+                Err(std::env::VarError::NotPresent) => Ok(None),
+                Err(err) => Err(AssError::invalid_env_var(var, err.to_string())),
+            }
+        };
+        let required = |suffix: &str| {
+            var(suffix)?.ok_or_else(|| AssError::missing_env_var(format!("{}_{}", prefix, suffix)))
+        };
+        let url = required("URL")?;
+        if let Err(err) = url.parse::<Url>() {
+            return Err(AssError::invalid_env_var(
+                format!("{}_URL", prefix),
+                err.to_string(),
+            ));
+        }
+        let acl = match var("ACL")? {
+            Some(acl) => acl.parse().map_err(|err: AssError| {
+                AssError::invalid_env_var(format!("{}_ACL", prefix), err.to_string())
+            })?,
+            None => Acl::default(),
+        };
+        Ok(AssClient::create(url, required("NAME")?, required("APIKEY")?)?.with_acl(acl))
+    }
+
+    /// Create a client from the named profile in the profiles file, see `Profiles`
+    pub fn from_profile(profile: &str) -> Result<Self, AssError> {
+        Profiles::discover()?.client(profile)
+    }
+
+    /// Create a client from the profile named by `ASS_PROFILE`, or the default profile in the
+    /// profiles file, see `Profiles`
+    pub fn from_default_profile() -> Result<Self, AssError> {
+        Profiles::discover()?.default_client()
+    }
+
+    /// Fetch the client's base url, always ending with a `/`
     pub fn url(&self) -> Url {
-        self.url
+        let mut url = self
+            .url
             .parse::<Url>()
-            .expect("Could not parse account URL")
+            .expect("Could not parse account URL");
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        url
+    }
+
+    /// The base url as configured, without validation
+    pub(crate) fn configured_url(&self) -> &str {
+        &self.url
     }
 
     /// Fetch the client's base url as `String`
     pub fn url_string(&self) -> String {
-        self.url
-            .parse::<Url>()
-            .expect("Could not parse account URL")
-            .to_string()
+        self.url().to_string()
     }
 
     /// Fetch base headers to use when interacting with the smooth storage
     pub(crate) fn get_headers(&self) -> Result<HeaderMap, AssError> {
         let mut headers = HeaderMap::new();
-        headers.insert("Authorization", format!("bearer {}", self.apikey).parse()?);
+        let mut authorization: HeaderValue =
+            format!("bearer {}", self.read_keys().primary.expose()).parse()?;
+        authorization.set_sensitive(true);
+        headers.insert("Authorization", authorization);
         headers.insert("Accept", "application/json".parse()?);
-        headers.insert("x-ass-acl", "public".parse()?);
+        headers.insert(ACL_HEADER, self.acl.to_string().parse()?);
 
         Ok(headers)
     }
@@ -93,23 +352,149 @@ impl AssClient {
     /// Sign the given url using the client's credentials.
     /// Ensures that the url matches the client's credentials before signing
     pub fn sign_url(&self, url: &str) -> Result<Url, AssError> {
-        let key = hmac::SigningKey::new(&digest::SHA256, &self.apikey.as_bytes());
-        let signature = hmac::sign(&key, url.as_bytes());
-        if !url.contains(&self.url) || !url.contains(&self.name) {
-            return Err(AssError::url_does_not_match_account(url.to_string()));
+        self.sign_url_with_params(url, &[])
+    }
+
+    /// Sign the given url, embedding an expiry timestamp in the signed payload.
+    /// The `expires` parameter is added as a unix timestamp before signing
+    pub fn sign_url_expiring(&self, url: &str, expires: DateTime<Utc>) -> Result<Url, AssError> {
+        let expires = expires.timestamp().to_string();
+        self.sign_url_with_params(url, &[(EXPIRES_PARAM, &expires)])
+    }
+
+    /// Sign the given url after appending the given query parameters, making the parameters
+    /// part of the signed payload
+    pub fn sign_url_with_params(
+        &self,
+        url: &str,
+        params: &[(&str, &str)],
+    ) -> Result<Url, AssError> {
+        let mut url = self.check_url_matches_account(url)?;
+        if url.query_pairs().any(|(k, _)| k == ACCESS_TOKEN_PARAM) {
+            return Err(AssError::url_already_signed(url.to_string()));
+        }
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
         }
+        let signing_key = signing_key(&self.read_keys().primary);
+        let signature = hmac::sign(&signing_key, url.as_str().as_bytes());
         let s: String = signature
             .as_ref()
             .iter()
             .map(|s| format!("{:02x}", s))
             .collect();
-        Url::parse_with_params(url, &[("accessToken", &s)]).map_err(|err| err.into())
+        url.query_pairs_mut().append_pair(ACCESS_TOKEN_PARAM, &s);
+        Ok(url)
     }
+
+    /// Verify a url signed with the client's credentials.
+    /// Checks that the url matches the client's account, that the access token is valid for the
+    /// rest of the url and that any embedded expiry time has not passed
+    pub fn verify_signed_url(&self, url: &str) -> Result<VerifiedUrl, AssError> {
+        self.verify_signed_url_at(url, Utc::now())
+    }
+
+    fn verify_signed_url_at(&self, url: &str, now: DateTime<Utc>) -> Result<VerifiedUrl, AssError> {
+        let parsed = Url::parse(url)?;
+        let token = parsed
+            .query_pairs()
+            .find(|(k, _)| k == ACCESS_TOKEN_PARAM)
+            .map(|(_, v)| v.into_owned())
+            .ok_or_else(|| AssError::missing_access_token(url.to_string()))?;
+
+        let mut unsigned = parsed.clone();
+        let query: Vec<&str> = parsed
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .filter(|pair| !pair.starts_with(&format!("{}=", ACCESS_TOKEN_PARAM)))
+            .collect();
+        if query.is_empty() {
+            unsigned.set_query(None);
+        } else {
+            unsigned.set_query(Some(&query.join("&")));
+        }
+        self.check_url_matches_account(unsigned.as_str())?;
+
+        let signature =
+            decode_hex(&token).ok_or_else(|| AssError::invalid_signature(url.to_string()))?;
+        let keys = self.read_keys();
+        let valid = std::iter::once(&keys.primary)
+            .chain(&keys.previous)
+            .any(|key| {
+                hmac::verify_with_own_key(
+                    &signing_key(key),
+                    unsigned.as_str().as_bytes(),
+                    &signature,
+                )
+                .is_ok()
+            });
+        if !valid {
+            return Err(AssError::invalid_signature(url.to_string()));
+        }
+
+        let expires = match unsigned.query_pairs().find(|(k, _)| k == EXPIRES_PARAM) {
+            Some((_, v)) => {
+                let timestamp = v
+                    .parse::<i64>()
+                    .map_err(|_| AssError::invalid_signature(url.to_string()))?;
+                let expires = Utc
+                    .timestamp_opt(timestamp, 0)
+                    .single()
+                    .ok_or_else(|| AssError::invalid_signature(url.to_string()))?;
+                if expires < now {
+                    return Err(AssError::signature_expired(url.to_string()));
+                }
+                Some(expires)
+            }
+            None => None,
+        };
+
+        Ok(VerifiedUrl {
+            url: unsigned,
+            expires,
+        })
+    }
+
+    /// Parses the url and checks that it points to a resource below this client's account, by
+    /// comparing scheme, host, port and base path and requiring the `users/{name}/` segments
+    fn check_url_matches_account(&self, url: &str) -> Result<Url, AssError> {
+        let parsed = Url::parse(url)?;
+        let base = self.url();
+        let account_path = base.join(&format!("users/{}/", self.name))?;
+        let matches = parsed.scheme() == base.scheme()
+            && parsed.host() == base.host()
+            && parsed.port_or_known_default() == base.port_or_known_default()
+            && parsed.path().starts_with(account_path.path())
+            && parsed.path().len() > account_path.path().len();
+        if !matches {
+            return Err(AssError::url_does_not_match_account(url.to_string()));
+        }
+        Ok(parsed)
+    }
+}
+
+fn signing_key(key: &Secret) -> hmac::SigningKey {
+    hmac::SigningKey::new(&digest::SHA256, key.expose().as_bytes())
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|b| b.len() == 2)
+                .and_then(|b| u8::from_str_radix(b, 16).ok())
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{AssClient, AssErrorKind};
+    use crate::client::MAX_PREVIOUS_APIKEYS;
+    use crate::{Acl, AssClient, AssErrorKind};
+    use chrono::{Duration, TimeZone, Utc};
 
     #[test]
     fn create_ass_client() {
@@ -117,7 +502,7 @@ mod tests {
             AssClient::create("http://url", "name", "key").expect("Could not get AssClient");
         assert_eq!(ass_client.url().to_string(), "http://url/");
         assert_eq!(ass_client.name, "name");
-        assert_eq!(ass_client.apikey, "key");
+        assert_eq!(ass_client.apikey().expose(), "key");
     }
 
     #[test]
@@ -126,7 +511,7 @@ mod tests {
             AssClient::from_file("./data/account.json").expect("Could not get AssClient");
         assert_eq!(ass_client.url().to_string(), "http://url/");
         assert_eq!(ass_client.name, "name");
-        assert_eq!(ass_client.apikey, "apikey");
+        assert_eq!(ass_client.apikey().expose(), "apikey");
     }
 
     #[test]
@@ -154,18 +539,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn authorization_header_is_sensitive() {
+        let ass_client =
+            AssClient::create("http://url", "name", "secret-key").expect("Could not get AssClient");
+
+        let headers = ass_client.get_headers().expect("Could not get headers");
+        assert!(headers
+            .get("Authorization")
+            .expect("Failed to get Authorization header")
+            .is_sensitive());
+        assert!(!format!("{:?}", headers).contains("secret-key"));
+    }
+
+    #[test]
+    fn get_headers_with_acl() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client
+            .expect("Could not get AssClient")
+            .with_acl(Acl::Private);
+
+        let headers = ass_client.get_headers().expect("Could not get headers");
+        assert_eq!(
+            headers
+                .get("x-ass-acl")
+                .expect("Failed to get x-ass-acl header"),
+            &"private"
+        );
+    }
+
     #[test]
     fn sign_url() {
         let ass_client = AssClient::from_file("./data/account.json");
         let ass_client = ass_client.expect("Could not get AssClient");
 
         let url = ass_client
-            .sign_url("http://url.com/name/image/2")
+            .sign_url("http://url/users/name/images/2")
             .expect("Could not sign url");
-        assert_eq!(url.to_string(), "http://url.com/name/image/2?accessToken=6ea029fcb85dd473116edbc80a500b99ef7f8c32dacbca51bf2be622a38ab6c9");
+        assert_eq!(url.to_string(), "http://url/users/name/images/2?accessToken=5ea22fc036c6b289dd4a4849d0be9bf12364ce9829702c09e2d909e85b15fd6e");
     }
 
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn sign_url_fails_on_wrong_ass_client_url() {
         let ass_client = AssClient::from_file("./data/account.json");
         let ass_client = ass_client.expect("Could not get AssClient");
@@ -178,4 +593,220 @@ mod tests {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn sign_url_fails_on_urls_outside_account() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client.expect("Could not get AssClient");
+
+        let urls = [
+            "http://url.com/users/name/images/2",
+            "https://url/users/name/images/2",
+            "http://url:8080/users/name/images/2",
+            "http://url/users/other/images/2",
+            "http://url/other/name/images/2",
+            "http://url/users/name/",
+        ];
+        for url in urls.iter() {
+            match ass_client.sign_url(url) {
+                Err(e) => assert!(matches!(e.kind, AssErrorKind::UrlDoesNotMatchAccount(_))),
+                _ => panic!("Signing {} should fail", url),
+            }
+        }
+    }
+
+    #[test]
+    fn sign_url_with_base_path() {
+        let ass_client = AssClient::create("http://url/base", "name", "apikey")
+            .expect("Could not get AssClient");
+
+        assert!(ass_client
+            .sign_url("http://url/base/users/name/images/2")
+            .is_ok());
+        assert!(ass_client
+            .sign_url("http://url/users/name/images/2")
+            .is_err());
+    }
+
+    #[test]
+    fn sign_url_fails_on_signed_url() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client.expect("Could not get AssClient");
+
+        let url = ass_client
+            .sign_url("http://url/users/name/images/2")
+            .expect("Could not sign url");
+        match ass_client.sign_url(url.as_str()) {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::UrlAlreadySigned(_))),
+            _ => panic!("Signing should fail"),
+        }
+    }
+
+    #[test]
+    fn sign_url_expiring() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client.expect("Could not get AssClient");
+
+        let expires = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        let url = ass_client
+            .sign_url_expiring("http://url/users/name/images/2", expires)
+            .expect("Could not sign url");
+        assert_eq!(
+            url.to_string(),
+            "http://url/users/name/images/2?expires=1600000000&accessToken=134d49a45a42d403f2a1131b404121a563140d67470f6f682b5ea0cb633f4545"
+        );
+    }
+
+    #[test]
+    fn verify_signed_url() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client.expect("Could not get AssClient");
+
+        let verified = ass_client
+            .verify_signed_url("http://url/users/name/images/2?accessToken=5ea22fc036c6b289dd4a4849d0be9bf12364ce9829702c09e2d909e85b15fd6e")
+            .expect("Could not verify url");
+        assert_eq!(verified.url.as_str(), "http://url/users/name/images/2");
+        assert_eq!(verified.expires, None);
+
+        match ass_client.verify_signed_url("http://url/users/name/images/3?accessToken=5ea22fc036c6b289dd4a4849d0be9bf12364ce9829702c09e2d909e85b15fd6e") {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidSignature(_))),
+            _ => panic!("Verification should fail"),
+        }
+
+        match ass_client.verify_signed_url("http://url/users/name/images/2") {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::MissingAccessToken(_))),
+            _ => panic!("Verification should fail"),
+        }
+    }
+
+    #[test]
+    fn verify_signed_url_checks_expiry() {
+        let ass_client = AssClient::from_file("./data/account.json");
+        let ass_client = ass_client.expect("Could not get AssClient");
+
+        let url = "http://url/users/name/images/2?expires=1600000000&accessToken=134d49a45a42d403f2a1131b404121a563140d67470f6f682b5ea0cb633f4545";
+        let expires = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+
+        let verified = ass_client
+            .verify_signed_url_at(url, expires - Duration::seconds(1))
+            .expect("Could not verify url");
+        assert_eq!(verified.expires, Some(expires));
+
+        match ass_client.verify_signed_url_at(url, expires + Duration::seconds(1)) {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::SignatureExpired(_))),
+            _ => panic!("Verification should fail"),
+        }
+    }
+
+    #[test]
+    fn ass_client_from_env() {
+        std::env::set_var("ASS_FROM_ENV_TEST_URL", "http://url");
+        std::env::set_var("ASS_FROM_ENV_TEST_NAME", "name");
+        std::env::set_var("ASS_FROM_ENV_TEST_APIKEY", "key");
+        std::env::set_var("ASS_FROM_ENV_TEST_ACL", "private");
+
+        let ass_client =
+            AssClient::from_env_with_prefix("ASS_FROM_ENV_TEST").expect("Could not get AssClient");
+        assert_eq!(ass_client.url_string(), "http://url/");
+        assert_eq!(ass_client.name, "name");
+        assert_eq!(ass_client.apikey().expose(), "key");
+        assert_eq!(ass_client.acl(), Acl::Private);
+    }
+
+    #[test]
+    fn ass_client_from_env_errors() {
+        let env = |url: &'static str| {
+            move |var: &str| match var {
+                "ASS_URL" => Ok(url.to_string()),
+                "ASS_NAME" => Ok("name".to_string()),
+                _ => Err(std::env::VarError::NotPresent),
+            }
+        };
+
+        match AssClient::from_env_vars("ASS", env("http://url")) {
+            Err(e) => assert_eq!(
+                e.kind,
+                AssErrorKind::MissingEnvVar("ASS_APIKEY".to_string())
+            ),
+            Ok(_) => panic!("Missing api key should fail"),
+        }
+        match AssClient::from_env_vars("ASS", env("not a url")) {
+            Err(e) => assert!(
+                matches!(e.kind, AssErrorKind::InvalidEnvVar { ref var, .. } if var == "ASS_URL")
+            ),
+            Ok(_) => panic!("Invalid url should fail"),
+        }
+    }
+
+    #[test]
+    fn rotate_apikey() {
+        let ass_client =
+            AssClient::create("http://url", "name", "apikey").expect("Could not get AssClient");
+        let old_url = ass_client
+            .sign_url("http://url/users/name/images/2")
+            .expect("Could not sign url");
+
+        let shared_client = ass_client.clone();
+        ass_client.rotate_apikey("new-apikey");
+        assert_eq!(shared_client.apikey().expose(), "new-apikey");
+
+        let new_url = shared_client
+            .sign_url("http://url/users/name/images/2")
+            .expect("Could not sign url");
+        assert_ne!(new_url, old_url);
+        shared_client
+            .verify_signed_url(old_url.as_str())
+            .expect("Could not verify url signed with previous key");
+        shared_client
+            .verify_signed_url(new_url.as_str())
+            .expect("Could not verify url signed with new key");
+
+        ass_client.set_apikeys("new-apikey", Vec::<String>::new());
+        match ass_client.verify_signed_url(old_url.as_str()) {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidSignature(_))),
+            _ => panic!("Verification should fail after the previous key is removed"),
+        }
+    }
+
+    #[test]
+    fn rotate_apikey_keeps_recent_previous_keys() {
+        let ass_client =
+            AssClient::create("http://url", "name", "key-0").expect("Could not get AssClient");
+        for i in 1..=MAX_PREVIOUS_APIKEYS + 2 {
+            ass_client.rotate_apikey(format!("key-{}", i));
+        }
+        let previous: Vec<String> = ass_client
+            .previous_apikeys()
+            .iter()
+            .map(|key| key.expose().to_string())
+            .collect();
+        let expected: Vec<String> = (0..MAX_PREVIOUS_APIKEYS)
+            .map(|i| format!("key-{}", MAX_PREVIOUS_APIKEYS + 1 - i))
+            .collect();
+        assert_eq!(previous, expected);
+    }
+
+    #[test]
+    fn with_previous_apikeys_does_not_change_clones() {
+        let ass_client =
+            AssClient::create("http://url", "name", "apikey").expect("Could not get AssClient");
+        let other = ass_client.clone().with_previous_apikeys(vec!["old-apikey"]);
+        assert!(ass_client.previous_apikeys().is_empty());
+        assert_eq!(other.previous_apikeys().len(), 1);
+
+        other.rotate_apikey("new-apikey");
+        assert_eq!(ass_client.apikey().expose(), "apikey");
+    }
+
+    #[test]
+    fn previous_apikeys_from_file() {
+        let ass_client = AssClient::from_file("./data/account_with_previous_keys.json")
+            .expect("Could not get AssClient");
+        assert_eq!(ass_client.apikey().expose(), "new-apikey");
+        assert_eq!(ass_client.previous_apikeys().len(), 1);
+
+        ass_client
+            .verify_signed_url("http://url/users/name/images/2?accessToken=5ea22fc036c6b289dd4a4849d0be9bf12364ce9829702c09e2d909e85b15fd6e")
+            .expect("Could not verify url signed with previous key");
+    }
 }
@@ -1,6 +1,7 @@
 use crate::AssError;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::str::FromStr;
 
@@ -47,6 +48,8 @@ pub struct FileData {
     pub original_url: String,
     pub created: DateTime<Utc>,
     pub updated: DateTime<Utc>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
 }
 
 impl FromStr for FileData {
@@ -1,13 +1,65 @@
 use derive_more::*;
 
 use std::error::Error;
+use std::time::Duration;
 
 /// The ASS error type, use the `.kind` value to check the cause of the error
-#[derive(Debug, Display)]
-#[display(fmt = "{}", kind)]
+#[derive(Debug)]
 pub struct AssError {
     pub kind: AssErrorKind,
     source: Option<Box<dyn Error + Send + Sync + 'static>>,
+    context: Option<Box<ErrorContext>>,
+}
+
+/// Details of the request to smooth storage that caused an error
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ErrorContext {
+    /// Name of the operation making the request, such as `upload_file`
+    pub operation: String,
+    /// HTTP method of the request
+    pub method: String,
+    /// Url of the request, with access tokens redacted
+    pub url: String,
+    /// Status code of the response, if one was received
+    pub status: Option<u16>,
+    /// Value of the response's `x-request-id` header
+    pub request_id: Option<String>,
+    /// Start of the response body
+    pub body: Option<String>,
+    /// Time to wait before retrying, from the response's `Retry-After` header
+    pub retry_after: Option<Duration>,
+}
+
+impl std::fmt::Display for AssError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "{}", self.kind)?;
+        let context = match &self.context {
+            Some(context) => context,
+            None => return Ok(()),
+        };
+        write!(
+            fmt,
+            " in {} ({} {}",
+            context.operation, context.method, context.url
+        )?;
+        if let Some(status) = context.status {
+            let reason = reqwest::StatusCode::from_u16(status)
+                .ok()
+                .and_then(|status| status.canonical_reason());
+            match reason {
+                Some(reason) => write!(fmt, ", status {} {}", status, reason)?,
+                None => write!(fmt, ", status {}", status)?,
+            }
+        }
+        if let Some(request_id) = &context.request_id {
+            write!(fmt, ", request id {}", request_id)?;
+        }
+        write!(fmt, ")")?;
+        if let Some(body) = &context.body {
+            write!(fmt, ": {}", body)?;
+        }
+        Ok(())
+    }
 }
 
 /// Describes what kind of error we're dealing with
@@ -15,12 +67,46 @@ pub struct AssError {
 pub enum AssErrorKind {
     #[display(fmt = "Invalid Account file: {}", file)]
     InvalidAccountFile { err: String, file: String },
+    #[display(fmt = "Missing environment variable {}", .0)]
+    MissingEnvVar(String),
+    #[display(fmt = "Invalid environment variable {}: {}", var, err)]
+    InvalidEnvVar { var: String, err: String },
+    #[display(fmt = "No profile named {}", .0)]
+    ProfileNotFound(String),
+    #[display(fmt = "No profiles file found")]
+    ConfigNotFound,
     #[display(fmt = "Error accessing file ({}): {}", file, err)]
     InvalidFileName { err: String, file: String },
+    #[display(fmt = "Invalid path ({}): {}", path, err)]
+    InvalidPath { err: String, path: String },
+    #[display(fmt = "File already exists: {}", .0)]
+    AlreadyExists(String),
+    #[display(fmt = "Not found: {}", .0)]
+    NotFound(String),
+    #[display(fmt = "Not a supported image: {}", .0)]
+    InvalidImage(String),
     #[display(fmt = "Url does not match the given account: {}", .0)]
     UrlDoesNotMatchAccount(String),
     #[display(fmt = "Invalid url")]
     InvalidUrl,
+    #[display(fmt = "Header can not be set as an extra header: {}", .0)]
+    ReservedHeader(String),
+    #[display(fmt = "Invalid content type: {}", .0)]
+    InvalidContentType(String),
+    #[display(fmt = "Invalid metadata key: {}", .0)]
+    InvalidMetadataKey(String),
+    #[display(fmt = "Invalid acl: {}", .0)]
+    InvalidAcl(String),
+    #[display(fmt = "Not a Smooth Storage url: {}", .0)]
+    InvalidAssUrl(String),
+    #[display(fmt = "Url is already signed: {}", .0)]
+    UrlAlreadySigned(String),
+    #[display(fmt = "Url is not signed: {}", .0)]
+    MissingAccessToken(String),
+    #[display(fmt = "Invalid url signature: {}", .0)]
+    InvalidSignature(String),
+    #[display(fmt = "Signed url has expired: {}", .0)]
+    SignatureExpired(String),
     #[display(fmt = "Reqwest Error")]
     ReqwestError,
     #[display(fmt = "IO Error")]
@@ -35,6 +121,127 @@ impl AssError {
         AssError {
             kind: AssErrorKind::UrlDoesNotMatchAccount(url),
             source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a header is reserved for the client or typed options
+    pub fn reserved_header(header: String) -> Self {
+        AssError {
+            kind: AssErrorKind::ReservedHeader(header),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a content type is not a valid mime type
+    pub fn invalid_content_type(
+        content_type: String,
+        err: impl Error + Send + Sync + 'static,
+    ) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidContentType(content_type),
+            source: Some(Box::new(err)),
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a metadata key contains invalid characters
+    pub fn invalid_metadata_key(key: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidMetadataKey(key),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that an acl value is unknown
+    pub fn invalid_acl(acl: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidAcl(acl),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a url could not be parsed as a Smooth Storage url
+    pub fn invalid_ass_url(url: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidAssUrl(url),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a storage path is invalid
+    pub fn invalid_path(path: String, err: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidPath { err, path },
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a file already exists at the given path
+    pub fn already_exists(path: String) -> Self {
+        AssError {
+            kind: AssErrorKind::AlreadyExists(path),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a file or image does not exist
+    pub fn not_found(resource: String) -> Self {
+        AssError {
+            kind: AssErrorKind::NotFound(resource),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a file is not an image in a supported format
+    pub fn invalid_image(file: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidImage(file),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a url already carries an access token
+    pub fn url_already_signed(url: String) -> Self {
+        AssError {
+            kind: AssErrorKind::UrlAlreadySigned(url),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a url carries no access token
+    pub fn missing_access_token(url: String) -> Self {
+        AssError {
+            kind: AssErrorKind::MissingAccessToken(url),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a url's access token does not match its content
+    pub fn invalid_signature(url: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidSignature(url),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a signed url is past its expiry time
+    pub fn signature_expired(url: String) -> Self {
+        AssError {
+            kind: AssErrorKind::SignatureExpired(url),
+            source: None,
+            context: None,
         }
     }
 
@@ -43,6 +250,43 @@ impl AssError {
         AssError {
             kind: AssErrorKind::InvalidFileName { err, file },
             source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a required environment variable is not set
+    pub fn missing_env_var(var: String) -> Self {
+        AssError {
+            kind: AssErrorKind::MissingEnvVar(var),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that an environment variable holds an invalid value
+    pub fn invalid_env_var(var: String, err: String) -> Self {
+        AssError {
+            kind: AssErrorKind::InvalidEnvVar { var, err },
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that a profile does not exist
+    pub fn profile_not_found(profile: String) -> Self {
+        AssError {
+            kind: AssErrorKind::ProfileNotFound(profile),
+            source: None,
+            context: None,
+        }
+    }
+
+    /// Creates an error indicating that no profiles file could be found
+    pub fn config_not_found() -> Self {
+        AssError {
+            kind: AssErrorKind::ConfigNotFound,
+            source: None,
+            context: None,
         }
     }
 
@@ -51,10 +295,87 @@ impl AssError {
         AssError {
             kind: AssErrorKind::InvalidAccountFile { err, file },
             source: None,
+            context: None,
         }
     }
 }
 
+impl AssError {
+    /// Details of the request that caused the error, if it was caused by a request
+    pub fn context(&self) -> Option<&ErrorContext> {
+        self.context.as_deref()
+    }
+
+    /// The HTTP status of the response that caused the error, if any
+    pub fn status(&self) -> Option<u16> {
+        self.context.as_ref().and_then(|context| context.status)
+    }
+
+    /// Check whether the error was caused by a missing file or image
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(404) || matches!(self.kind, AssErrorKind::NotFound(_))
+    }
+
+    /// Check whether the error was caused by the storage rejecting the client's credentials
+    pub fn is_auth_error(&self) -> bool {
+        matches!(self.status(), Some(401) | Some(403))
+    }
+
+    /// Check whether the failed operation may succeed if retried: timeouts, connection errors,
+    /// throttling and temporary server errors
+    pub fn is_retryable(&self) -> bool {
+        if let Some(status) = self.status() {
+            return matches!(status, 408 | 429 | 500 | 502 | 503 | 504);
+        }
+        let source = match &self.source {
+            Some(source) => source,
+            None => return false,
+        };
+        if let Some(err) = source.downcast_ref::<reqwest::Error>() {
+            return err.is_timeout() || err.is_connect() || is_connection_error(err);
+        }
+        if let Some(err) = source.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::TimedOut | std::io::ErrorKind::Interrupted
+            );
+        }
+        false
+    }
+
+    /// How long the storage asked the client to wait before retrying, if it did
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.context
+            .as_ref()
+            .and_then(|context| context.retry_after)
+    }
+
+    /// Attach request details to the error
+    pub(crate) fn with_context(mut self, context: ErrorContext) -> Self {
+        self.context = Some(Box::new(context));
+        self
+    }
+}
+
+/// Check whether a request failed because the connection was refused, reset or timed out
+fn is_connection_error(err: &reqwest::Error) -> bool {
+    let mut source = err.source();
+    while let Some(err) = source {
+        if let Some(err) = err.downcast_ref::<std::io::Error>() {
+            return matches!(
+                err.kind(),
+                std::io::ErrorKind::ConnectionRefused
+                    | std::io::ErrorKind::ConnectionReset
+                    | std::io::ErrorKind::ConnectionAborted
+                    | std::io::ErrorKind::BrokenPipe
+                    | std::io::ErrorKind::TimedOut
+            );
+        }
+        source = err.source();
+    }
+    false
+}
+
 impl Error for AssError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         self.source
@@ -65,7 +386,11 @@ impl Error for AssError {
 
 impl From<AssErrorKind> for AssError {
     fn from(kind: AssErrorKind) -> AssError {
-        AssError { kind, source: None }
+        AssError {
+            kind,
+            source: None,
+            context: None,
+        }
     }
 }
 
@@ -74,6 +399,7 @@ impl From<reqwest::Error> for AssError {
         AssError {
             kind: AssErrorKind::ReqwestError,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -83,6 +409,7 @@ impl From<url::ParseError> for AssError {
         AssError {
             kind: AssErrorKind::InvalidUrl,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -92,6 +419,7 @@ impl From<reqwest::header::InvalidHeaderValue> for AssError {
         AssError {
             kind: AssErrorKind::ReqwestError,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -101,6 +429,7 @@ impl From<reqwest::header::InvalidHeaderName> for AssError {
         AssError {
             kind: AssErrorKind::ReqwestError,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -110,6 +439,7 @@ impl From<std::io::Error> for AssError {
         AssError {
             kind: AssErrorKind::IOError,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -119,6 +449,72 @@ impl From<serde_json::error::Error> for AssError {
         AssError {
             kind: AssErrorKind::JsonError,
             source: Some(Box::new(err)),
+            context: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AssError, AssErrorKind, ErrorContext};
+    use std::time::Duration;
+
+    fn status_error(status: u16) -> AssError {
+        AssError::from(AssErrorKind::ReqwestError).with_context(ErrorContext {
+            status: Some(status),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn classify_status_errors() {
+        assert!(status_error(404).is_not_found());
+        assert!(!status_error(404).is_retryable());
+        assert!(status_error(401).is_auth_error());
+        assert!(status_error(403).is_auth_error());
+        for status in [429, 500, 503].iter() {
+            assert!(status_error(*status).is_retryable());
+        }
+        assert!(!status_error(400).is_retryable());
+        assert!(AssError::not_found("files/1".to_string()).is_not_found());
+    }
+
+    #[test]
+    fn classify_io_errors() {
+        let timeout = AssError::from(std::io::Error::from(std::io::ErrorKind::TimedOut));
+        assert!(timeout.is_retryable());
+        let missing = AssError::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(!missing.is_retryable());
+        assert!(!AssError::from(AssErrorKind::JsonError).is_retryable());
+    }
+
+    #[test]
+    fn retry_after_from_response() {
+        let _m = mockito::mock("GET", "/images/8")
+            .with_status(429)
+            .with_header("retry-after", "30")
+            .create();
+
+        let ass_client = crate::AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient");
+        let err = match tokio_test::block_on(crate::image_handling::get_image_information(
+            &ass_client,
+            8,
+        )) {
+            Err(err) => err,
+            Ok(_) => panic!("Request should fail"),
+        };
+        assert!(err.is_retryable());
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn connection_errors_are_retryable() {
+        let ass_client = crate::AssClient::create("http://127.0.0.1:1", "account", "apikey")
+            .expect("Could not get AssClient");
+        match tokio_test::block_on(crate::image_handling::get_image_information(&ass_client, 8)) {
+            Err(err) => assert!(err.is_retryable()),
+            Ok(_) => panic!("Request should fail"),
         }
     }
 }
@@ -2,124 +2,267 @@
 //! Module for working with files in smooth storage
 //!
 
-use crate::{image_handling, AssClient, AssError, AssErrorKind, FileData, ImageData};
-use reqwest::multipart::Form;
-use reqwest::Url;
+use crate::request::Request;
+use crate::upload_options::{validate_metadata_key, ORIGINAL_FILENAME_KEY};
+use crate::{
+    image_handling, Acl, AssClient, AssError, AssErrorKind, AssPath, CollisionPolicy, FileData,
+    ImageData, UploadOptions,
+};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::multipart::{Form, Part};
+use reqwest::{Method, Url};
 use serde_json::Value;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Number of numeric suffixes to try when renaming a file to avoid a collision
+const MAX_RENAME_ATTEMPTS: u32 = 100;
 
 /// Search for files
 pub async fn search(
     ass_client: &AssClient,
     queries: &[(&str, &str)],
 ) -> Result<Vec<FileData>, AssError> {
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join("files")?;
-    let url = Url::parse_with_params(url.as_str(), queries)?;
-
-    let res = client.get(url).send().await?;
-
-    let data: Vec<FileData> = res.json().await?;
+    Request::api(ass_client, "search", Method::GET, "files")?
+        .query(queries)
+        .send_json()
+        .await
+}
 
-    Ok(data)
+/// Search for files with the given metadata values
+pub async fn search_by_metadata(
+    ass_client: &AssClient,
+    metadata: &[(&str, &str)],
+) -> Result<Vec<FileData>, AssError> {
+    let mut queries = Vec::with_capacity(metadata.len());
+    for (key, value) in metadata {
+        validate_metadata_key(key)?;
+        queries.push((format!("metadata[{}]", key), *value));
+    }
+    let queries: Vec<(&str, &str)> = queries.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+    search(ass_client, &queries).await
 }
 
 /// Upload file without any headers
-pub async fn upload_file<T: Into<PathBuf>>(
+pub async fn upload_file<T: Into<PathBuf>, D: AsRef<str>>(
     ass_client: &AssClient,
     path: T,
-    destination: &str,
+    destination: D,
 ) -> Result<FileData, AssError> {
-    let path = path.into();
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("files/{}", destination))?;
-    let file_name = get_filename_from_path(&path)?;
-    let url = url.join(file_name)?;
-
-    let stream = std::fs::read(path)?;
-    let form = Form::new().part("file", reqwest::multipart::Part::stream(stream));
-
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
+    upload_file_with_options(ass_client, path, destination, &UploadOptions::new()).await
+}
 
-    let res = client.post(url).multipart(form).send().await?;
+/// Upload file with the given acl, overriding the client's default acl
+pub async fn upload_file_with_acl<T: Into<PathBuf>, D: AsRef<str>>(
+    ass_client: &AssClient,
+    path: T,
+    destination: D,
+    acl: Acl,
+) -> Result<FileData, AssError> {
+    let options = UploadOptions::new().acl(acl);
+    upload_file_with_options(ass_client, path, destination, &options).await
+}
 
-    let data: FileData = res.json().await?;
-    Ok(data)
+/// Upload file with the given upload options
+pub async fn upload_file_with_options<T: Into<PathBuf>, D: AsRef<str>>(
+    ass_client: &AssClient,
+    path: T,
+    destination: D,
+    options: &UploadOptions,
+) -> Result<FileData, AssError> {
+    let path = path.into();
+    let destination = AssPath::parse(destination.as_ref())?;
+    let (file_name, options) = prepare_file_name(&path, options)?;
+    let file_name = file_name.as_str();
+    let remote_path = match options.get_collision_policy() {
+        None | Some(CollisionPolicy::Overwrite) => destination.join(file_name)?,
+        Some(CollisionPolicy::Fail) => {
+            let remote_path = destination.join(file_name)?;
+            if find_file_by_path(ass_client, &remote_path).await?.is_some() {
+                return Err(AssError::already_exists(remote_path.to_string()));
+            }
+            remote_path
+        }
+        Some(CollisionPolicy::Skip) => {
+            let remote_path = destination.join(file_name)?;
+            if let Some(existing) = find_file_by_path(ass_client, &remote_path).await? {
+                return Ok(existing);
+            }
+            remote_path
+        }
+        Some(CollisionPolicy::Rename) => {
+            find_available_path(ass_client, &destination, file_name).await?
+        }
+    };
+    let content = std::fs::read(&path)?;
+    let length = content.len();
+    send_file(
+        ass_client,
+        &remote_path,
+        options.to_form(content)?,
+        length,
+        options.to_headers()?,
+    )
+    .await
 }
 
 /// Upload file with defaults headers
-pub async fn upload_file_with_headers<T: Into<PathBuf>>(
+#[deprecated(note = "use `upload_file_with_options` instead")]
+pub async fn upload_file_with_headers<T: Into<PathBuf>, D: AsRef<str>>(
     ass_client: &AssClient,
     path: T,
-    destination: &str,
+    destination: D,
     headers: &[(&str, &str)],
 ) -> Result<FileData, AssError> {
     let path = path.into();
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("files/{}", destination))?;
-    let file_name = get_filename_from_path(&path)?;
-    let url = url.join(file_name)?;
-
-    let stream = std::fs::read(path)?;
-    let form = Form::new().part("file", reqwest::multipart::Part::stream(stream));
-
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-
-    let mut builder = client.post(url).multipart(form);
-
+    let destination = AssPath::parse(destination.as_ref())?;
+    let mut header_map = HeaderMap::new();
     for (k, v) in headers {
-        builder = builder.header(*k, *v);
+        header_map.append(HeaderName::from_bytes(k.as_bytes())?, v.parse()?);
     }
+    let remote_path = destination.join(get_filename_from_path(&path)?)?;
+    let content = std::fs::read(&path)?;
+    let length = content.len();
+    let form = Form::new().part("file", Part::stream(content));
+    send_file(ass_client, &remote_path, form, length, header_map).await
+}
 
-    let res = builder.send().await?;
-
-    let data: FileData = res.json().await?;
-    Ok(data)
+async fn send_file(
+    ass_client: &AssClient,
+    remote_path: &AssPath,
+    form: Form,
+    length: usize,
+    headers: HeaderMap,
+) -> Result<FileData, AssError> {
+    Request::api(
+        ass_client,
+        "upload_file",
+        Method::POST,
+        &format!("files/{}", remote_path.to_url_path()),
+    )?
+    .headers(headers)
+    .multipart(form, length)
+    .send_json()
+    .await
 }
 
 /// Gets signed url for a file on the given path
-pub fn get_file_url(ass_client: &AssClient, path: &str) -> Result<String, AssError> {
+pub fn get_file_url<P: AsRef<str>>(ass_client: &AssClient, path: P) -> Result<String, AssError> {
+    let path = AssPath::parse(path.as_ref())?;
     let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("users/{}/files/{}", ass_client.name, path))?;
-    let url = ass_client.sign_url(&url.as_str())?;
+    let url = url.join(&format!(
+        "users/{}/files/{}",
+        ass_client.name,
+        path.to_url_path()
+    ))?;
+    let url = ass_client.sign_url(url.as_str())?;
     Ok(url.to_string())
 }
 
+/// Gets signed url for a file on the given path, valid until `expires`
+pub fn get_file_url_expiring<P: AsRef<str>>(
+    ass_client: &AssClient,
+    path: P,
+    expires: DateTime<Utc>,
+) -> Result<String, AssError> {
+    let path = AssPath::parse(path.as_ref())?;
+    let url = Url::parse(&ass_client.url_string())?;
+    let url = url.join(&format!(
+        "users/{}/files/{}",
+        ass_client.name,
+        path.to_url_path()
+    ))?;
+    let url = ass_client.sign_url_expiring(url.as_str(), expires)?;
+    Ok(url.to_string())
+}
+
+/// Replace the custom metadata of an existing file
+pub async fn update_file_metadata(
+    ass_client: &AssClient,
+    id: u64,
+    metadata: &HashMap<String, String>,
+) -> Result<FileData, AssError> {
+    for key in metadata.keys() {
+        validate_metadata_key(key)?;
+    }
+    Request::api(
+        ass_client,
+        "update_file_metadata",
+        Method::PUT,
+        &format!("files/{}/metadata", id),
+    )?
+    .json(metadata)?
+    .send_json()
+    .await
+}
+
+/// Change the acl of an existing file
+pub async fn set_file_acl(ass_client: &AssClient, id: u64, acl: Acl) -> Result<FileData, AssError> {
+    Request::api(
+        ass_client,
+        "set_file_acl",
+        Method::PUT,
+        &format!("files/{}/acl", id),
+    )?
+    .json(&serde_json::json!({ "acl": acl }))?
+    .send_json()
+    .await
+}
+
 /// Returns file information, queried by id
 pub async fn get_file_information_by_id(
     ass_client: &AssClient,
     id: u64,
 ) -> Result<FileData, AssError> {
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("files/{}", id))?;
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-    let res = client.get(url).send().await?;
-    let data: FileData = res.json().await?;
-    Ok(data)
+    Request::api(
+        ass_client,
+        "get_file_information_by_id",
+        Method::GET,
+        &format!("files/{}", id),
+    )?
+    .send_json()
+    .await
 }
 
 /// Returns file information, queried by path
-pub async fn get_file_information_by_path(
+pub async fn get_file_information_by_path<P: AsRef<str>>(
     ass_client: &AssClient,
-    path: &str,
+    path: P,
 ) -> Result<FileData, AssError> {
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("files/path/{}", path))?;
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-    let res = client.get(url).send().await?;
-    let data: FileData = res.json().await?;
-    Ok(data)
+    let path = AssPath::parse(path.as_ref())?;
+    Request::api(
+        ass_client,
+        "get_file_information_by_path",
+        Method::GET,
+        &format!("files/path/{}", path.to_url_path()),
+    )?
+    .send_json()
+    .await
+}
+
+/// Download the content of the file on the given path, through a signed url
+pub async fn download_file<P: AsRef<str>>(
+    ass_client: &AssClient,
+    path: P,
+) -> Result<Vec<u8>, AssError> {
+    let url = get_file_url(ass_client, path)?;
+    Request::signed(ass_client, "download_file", &url)?
+        .send_bytes()
+        .await
+}
+
+/// Delete a file
+pub async fn delete_file(ass_client: &AssClient, id: u64) -> Result<(), AssError> {
+    Request::api(
+        ass_client,
+        "delete_file",
+        Method::DELETE,
+        &format!("files/{}", id),
+    )?
+    .send()
+    .await?;
+    Ok(())
 }
 
 /// Returns a default image rendition of a file
@@ -127,16 +270,14 @@ pub async fn get_file_rendition(
     ass_client: &AssClient,
     file_id: u64,
 ) -> Result<ImageData, AssError> {
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("files/{}/image", file_id))?;
-
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-
-    let res = client.get(url).send().await?;
-
-    let data: Value = res.json().await?;
+    let data: Value = Request::api(
+        ass_client,
+        "get_file_rendition",
+        Method::GET,
+        &format!("files/{}/image", file_id),
+    )?
+    .send_json()
+    .await?;
     image_handling::get_image_information(
         ass_client,
         data.get("image_id")
@@ -147,7 +288,80 @@ pub async fn get_file_rendition(
     .await
 }
 
-fn get_filename_from_path(path: &PathBuf) -> Result<&str, AssError> {
+/// Fetch file information for a path, returning `None` if there is no file at the path
+async fn find_file_by_path(
+    ass_client: &AssClient,
+    path: &AssPath,
+) -> Result<Option<FileData>, AssError> {
+    let result = Request::api(
+        ass_client,
+        "find_file_by_path",
+        Method::GET,
+        &format!("files/path/{}", path.to_url_path()),
+    )?
+    .send_json()
+    .await;
+    match result {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.is_not_found() => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Find the first of `name.ext`, `name-1.ext`, `name-2.ext`, ... that does not exist in the
+/// destination
+async fn find_available_path(
+    ass_client: &AssClient,
+    destination: &AssPath,
+    file_name: &str,
+) -> Result<AssPath, AssError> {
+    for candidate in rename_candidates(file_name) {
+        let remote_path = destination.join(&candidate)?;
+        if find_file_by_path(ass_client, &remote_path).await?.is_none() {
+            return Ok(remote_path);
+        }
+    }
+    Err(AssError::already_exists(
+        destination.join(file_name)?.to_string(),
+    ))
+}
+
+/// The names to try, in order, when renaming an upload to avoid a collision: `name.ext`,
+/// `name-1.ext`, `name-2.ext`, ...
+pub(crate) fn rename_candidates(file_name: &str) -> impl Iterator<Item = String> + '_ {
+    let (stem, extension) = match file_name.rfind('.') {
+        Some(i) if i > 0 => (&file_name[..i], &file_name[i..]),
+        _ => (file_name, ""),
+    };
+    std::iter::once(file_name.to_string()).chain(
+        (1..=MAX_RENAME_ATTEMPTS).map(move |attempt| format!("{}-{}{}", stem, attempt, extension)),
+    )
+}
+
+/// Get the name to upload the file at `path` as, sanitized if the options ask for it. When the
+/// name changes, the original name is added to the metadata of the returned options
+pub(crate) fn prepare_file_name(
+    path: &Path,
+    options: &UploadOptions,
+) -> Result<(String, UploadOptions), AssError> {
+    let original_name = get_filename_from_path(path)?;
+    match options.get_sanitizer() {
+        Some(sanitizer) => {
+            let file_name = sanitizer.sanitize(original_name);
+            if file_name == original_name {
+                Ok((file_name, options.clone()))
+            } else {
+                let options = options
+                    .clone()
+                    .metadata(ORIGINAL_FILENAME_KEY, original_name)?;
+                Ok((file_name, options))
+            }
+        }
+        None => Ok((original_name.to_string(), options.clone())),
+    }
+}
+
+fn get_filename_from_path(path: &Path) -> Result<&str, AssError> {
     path.file_name().and_then(|s| s.to_str()).ok_or_else(|| {
         AssError::invalid_file_name(
             "Error parsing filename".to_string(),
@@ -160,8 +374,12 @@ fn get_filename_from_path(path: &PathBuf) -> Result<&str, AssError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{file_handling, AssClient};
+    use crate::{
+        file_handling, Acl, AssClient, AssErrorKind, CacheControl, CollisionPolicy,
+        ContentDisposition, FilenameSanitizer, UploadOptions,
+    };
     use mockito;
+    use std::time::Duration;
 
     macro_rules! aw {
         ($e:expr) => {
@@ -193,6 +411,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn test_file_upload_with_headers() {
         let _m = mockito::mock("POST", "/files/file-path/account.json")
             .match_header("Authorization", "bearer apikey")
@@ -216,4 +435,382 @@ mod tests {
         .expect("Could not get result");
         assert_eq!(result.id, 2);
     }
+
+    #[test]
+    fn test_file_upload_with_options() {
+        let _m = mockito::mock("POST", "/files/file-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .match_header("Cache-Control", "max-age=234")
+            .match_header("Content-Disposition", "attachment; filename=\"account.json\"")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get Account");
+
+        let options = UploadOptions::new()
+            .cache_control(CacheControl::max_age(Duration::from_secs(234)))
+            .content_disposition(ContentDisposition::attachment("account.json"));
+
+        let result = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "file-path/",
+            &options,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 2);
+    }
+
+    #[test]
+    fn test_file_upload_with_acl() {
+        let _m = mockito::mock("POST", "/files/private-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .match_header("x-ass-acl", "private")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let result = aw!(file_handling::upload_file_with_acl(
+            &ass_client,
+            "./data/account.json",
+            "private-path/",
+            Acl::Private,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 2);
+    }
+
+    #[test]
+    fn test_set_file_acl() {
+        let _m = mockito::mock("PUT", "/files/2/acl")
+            .match_header("Authorization", "bearer apikey")
+            .match_body("{\"acl\":\"private\"}")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let result = aw!(file_handling::set_file_acl(&ass_client, 2, Acl::Private))
+            .expect("Could not get result");
+        assert_eq!(result.id, 2);
+    }
+
+    #[test]
+    fn test_file_upload_with_metadata() {
+        let _m = mockito::mock("POST", "/files/metadata-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .match_body(mockito::Matcher::Regex(
+                "name=\"metadata\"\r\n\r\n\\{\"article_id\":\"12\",\"license\":\"CC-BY\"\\}".to_string(),
+            ))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\", \"metadata\": {\"article_id\": \"12\", \"license\": \"CC-BY\"}}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let options = UploadOptions::new()
+            .metadata("article_id", "12")
+            .and_then(|options| options.metadata("license", "CC-BY"))
+            .expect("Could not set metadata");
+
+        let result = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "metadata-path/",
+            &options,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.metadata.get("article_id"), Some(&"12".to_string()));
+    }
+
+    #[test]
+    fn test_search_by_metadata() {
+        let _m = mockito::mock("GET", "/files?metadata%5Barticle_id%5D=12")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("[{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\", \"metadata\": {\"article_id\": \"12\"}}]")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let result = aw!(file_handling::search_by_metadata(
+            &ass_client,
+            &[("article_id", "12")]
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, 2);
+    }
+
+    #[test]
+    fn test_file_upload_fails_on_existing_file() {
+        let _m = mockito::mock("GET", "/files/path/fail-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 7, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Fail);
+        match aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "fail-path/",
+            &options,
+        )) {
+            Err(e) => assert_eq!(
+                e.kind,
+                AssErrorKind::AlreadyExists("fail-path/account.json".to_string())
+            ),
+            _ => panic!("Upload should fail"),
+        }
+    }
+
+    #[test]
+    fn test_collision_check_error_context() {
+        let _m = mockito::mock("GET", "/files/path/broken-path/account.json")
+            .with_status(500)
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Fail);
+        let err = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "broken-path/",
+            &options,
+        ))
+        .expect_err("Upload should fail");
+        assert_eq!(
+            err.context().map(|context| context.operation.as_str()),
+            Some("find_file_by_path")
+        );
+    }
+
+    #[test]
+    fn test_file_upload_skips_existing_file() {
+        let _m = mockito::mock("GET", "/files/path/skip-path/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 7, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+        let upload = mockito::mock("POST", "/files/skip-path/account.json")
+            .expect(0)
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Skip);
+        let result = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "skip-path/",
+            &options,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 7);
+        upload.assert();
+    }
+
+    #[test]
+    fn test_file_upload_renames_on_existing_file() {
+        let _m1 = mockito::mock("GET", "/files/path/rename-path/account.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 7, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+        let _m2 = mockito::mock("GET", "/files/path/rename-path/account-1.json")
+            .with_status(404)
+            .create();
+        let _m3 = mockito::mock("POST", "/files/rename-path/account-1.json")
+            .match_header("x-ass-overwrite", "false")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 8, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Rename);
+        let result = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "rename-path/",
+            &options,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 8);
+    }
+
+    #[test]
+    fn test_file_upload_without_trailing_slash() {
+        let _m = mockito::mock("POST", "/files/no-slash/nested/account.json")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let result = aw!(file_handling::upload_file(
+            &ass_client,
+            "./data/account.json",
+            "/no-slash//nested"
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 2);
+
+        match aw!(file_handling::upload_file(
+            &ass_client,
+            "./data/account.json",
+            "no-slash/../other"
+        )) {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidPath { .. })),
+            _ => panic!("Upload should fail"),
+        }
+    }
+
+    #[test]
+    fn test_file_upload_with_sanitized_name() {
+        let _m = mockito::mock("POST", "/files/sanitized/bloebaer-2.json")
+            .match_header("Authorization", "bearer apikey")
+            .match_body(mockito::Matcher::Regex(
+                "\\{\"original_filename\":\"Bløbær #2.json\"\\}".to_string(),
+            ))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let dir = std::env::temp_dir().join("ass-rs-sanitize-test");
+        std::fs::create_dir_all(&dir).expect("Could not create directory");
+        let path = dir.join("Bløbær #2.json");
+        std::fs::copy("./data/account.json", &path).expect("Could not copy file");
+
+        let options = UploadOptions::new()
+            .sanitize_filename(FilenameSanitizer::new().slugify(true).lowercase(true));
+        let result = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            &path,
+            "sanitized",
+            &options,
+        ))
+        .expect("Could not get result");
+        assert_eq!(result.id, 2);
+    }
+
+    #[test]
+    fn test_get_file_url_encodes_path() {
+        let ass_client =
+            AssClient::create("http://url", "account", "apikey").expect("Could not get AssClient");
+
+        let url = file_handling::get_file_url(&ass_client, "reports/my report.pdf")
+            .expect("Could not get url");
+        assert!(url.starts_with("http://url/users/account/files/reports/my%20report.pdf?"));
+
+        let url = file_handling::get_file_url(&ass_client, "reports/rapport%20final.pdf")
+            .expect("Could not get url");
+        assert!(url.starts_with("http://url/users/account/files/reports/rapport%2520final.pdf?"));
+    }
+
+    #[test]
+    fn test_download_file() {
+        let _m = mockito::mock("GET", "/users/account/files/reports/q1.txt")
+            .match_query(mockito::Matcher::Regex("accessToken=".to_string()))
+            .with_status(200)
+            .with_body("report")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let content = aw!(file_handling::download_file(&ass_client, "reports/q1.txt"))
+            .expect("Could not download file");
+        assert_eq!(content, b"report");
+    }
+
+    #[test]
+    fn test_error_context() {
+        let _m = mockito::mock("GET", "/files/17")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(500)
+            .with_header("x-request-id", "req-123")
+            .with_body("{\"error\": \"database unavailable\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let ass_client =
+            AssClient::create(url, "account", "apikey").expect("Could not get AssClient");
+
+        let err = match aw!(file_handling::get_file_information_by_id(&ass_client, 17)) {
+            Err(err) => err,
+            Ok(_) => panic!("Request should fail"),
+        };
+        assert_eq!(err.kind, AssErrorKind::ReqwestError);
+        let context = err.context().expect("Error should have context");
+        assert_eq!(context.operation, "get_file_information_by_id");
+        assert_eq!(context.method, "GET");
+        assert_eq!(context.url, format!("{}/files/17", url));
+        assert_eq!(context.status, Some(500));
+        assert_eq!(context.request_id.as_deref(), Some("req-123"));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Reqwest Error in get_file_information_by_id (GET {}/files/17, status 500 Internal Server Error, request id req-123): {{\"error\": \"database unavailable\"}}",
+                url
+            )
+        );
+    }
 }
@@ -0,0 +1,259 @@
+///
+/// Sanitizes file names before upload, so they produce valid and readable urls.
+///
+/// Characters that break urls, such as `#`, `?`, `%` and control characters, are always
+/// replaced. By default non-ascii letters are transliterated and the extension is preserved.
+///
+/// ```rust
+/// use ass_rs::FilenameSanitizer;
+///
+/// let sanitizer = FilenameSanitizer::new();
+/// assert_eq!(sanitizer.sanitize("Årsrapport #2 (ærlig).PDF"), "Aarsrapport _2 (aerlig).PDF");
+///
+/// let sanitizer = FilenameSanitizer::new().slugify(true).lowercase(true).max_length(Some(16));
+/// assert_eq!(sanitizer.sanitize("Årsrapport #2 (ærlig).PDF"), "aarsrapport.pdf");
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilenameSanitizer {
+    transliterate: bool,
+    slugify: bool,
+    lowercase: bool,
+    max_length: Option<usize>,
+    preserve_extension: bool,
+}
+
+impl Default for FilenameSanitizer {
+    fn default() -> Self {
+        FilenameSanitizer {
+            transliterate: true,
+            slugify: false,
+            lowercase: false,
+            max_length: None,
+            preserve_extension: true,
+        }
+    }
+}
+
+impl FilenameSanitizer {
+    /// Create a sanitizer with default settings
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Replace non-ascii letters with ascii equivalents, such as `æ` with `ae`
+    pub fn transliterate(mut self, transliterate: bool) -> Self {
+        self.transliterate = transliterate;
+        self
+    }
+
+    /// Replace everything except ascii letters, digits, `-`, `_` and `.` with `-`
+    pub fn slugify(mut self, slugify: bool) -> Self {
+        self.slugify = slugify;
+        self
+    }
+
+    /// Convert the name to lowercase
+    pub fn lowercase(mut self, lowercase: bool) -> Self {
+        self.lowercase = lowercase;
+        self
+    }
+
+    /// Limit the length of the name, in characters, including the extension. An extension that
+    /// leaves no room for the rest of the name is dropped, and a limit of 0 is treated as 1
+    pub fn max_length(mut self, max_length: Option<usize>) -> Self {
+        self.max_length = max_length;
+        self
+    }
+
+    /// Keep the extension intact when slugifying and truncating
+    pub fn preserve_extension(mut self, preserve_extension: bool) -> Self {
+        self.preserve_extension = preserve_extension;
+        self
+    }
+
+    /// Sanitize a file name
+    pub fn sanitize(&self, file_name: &str) -> String {
+        let (stem, extension) = match file_name.rfind('.') {
+            Some(i) if self.preserve_extension && i > 0 => {
+                (&file_name[..i], Some(&file_name[i + 1..]))
+            }
+            _ => (file_name, None),
+        };
+
+        let mut extension = extension
+            .map(|e| self.sanitize_part(e))
+            .filter(|e| !e.is_empty());
+        let mut stem = self.sanitize_part(stem);
+        if stem.is_empty() {
+            stem = "file".to_string();
+        }
+
+        if let Some(max_length) = self.max_length {
+            let max_length = max_length.max(1);
+            let extension_length = extension.as_ref().map(|e| e.chars().count() + 1);
+            // Drop an extension that leaves no room for the stem
+            if extension_length.unwrap_or(0) >= max_length {
+                extension = None;
+            }
+            let stem_length = match &extension {
+                Some(extension) => max_length - extension.chars().count() - 1,
+                None => max_length,
+            };
+            stem = stem.chars().take(stem_length).collect();
+            if self.slugify {
+                stem = stem.trim_end_matches('-').to_string();
+            }
+        }
+
+        match extension {
+            Some(extension) => format!("{}.{}", stem, extension),
+            None => stem,
+        }
+    }
+
+    fn sanitize_part(&self, part: &str) -> String {
+        let mut sanitized = String::with_capacity(part.len());
+        for c in part.chars() {
+            match transliterate(c) {
+                Some(replacement) if self.transliterate => sanitized.push_str(replacement),
+                _ => sanitized.push(c),
+            }
+        }
+
+        let mut sanitized: String = if self.slugify {
+            let slug: String = sanitized
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                        c
+                    } else {
+                        '-'
+                    }
+                })
+                .collect();
+            slug.split('-')
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        } else {
+            sanitized
+                .chars()
+                .map(|c| {
+                    if c.is_control() || "#?%/\\\"<>|*:".contains(c) {
+                        '_'
+                    } else {
+                        c
+                    }
+                })
+                .collect::<String>()
+                .trim()
+                .to_string()
+        };
+
+        if self.lowercase {
+            sanitized = sanitized.to_lowercase();
+        }
+        sanitized
+    }
+}
+
+/// Ascii replacement for common non-ascii latin letters
+fn transliterate(c: char) -> Option<&'static str> {
+    let replacement = match c {
+        'æ' => "ae",
+        'Æ' => "Ae",
+        'ø' => "oe",
+        'Ø' => "Oe",
+        'å' => "aa",
+        'Å' => "Aa",
+        'ä' => "ae",
+        'Ä' => "Ae",
+        'ö' => "oe",
+        'Ö' => "Oe",
+        'ü' => "ue",
+        'Ü' => "Ue",
+        'ß' => "ss",
+        'à' | 'á' | 'â' | 'ã' | 'ā' => "a",
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ā' => "A",
+        'ç' | 'ć' | 'č' => "c",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ð' | 'đ' => "d",
+        'Ð' | 'Đ' => "D",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' => "e",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ę' => "E",
+        'ì' | 'í' | 'î' | 'ï' => "i",
+        'Ì' | 'Í' | 'Î' | 'Ï' => "I",
+        'ł' => "l",
+        'Ł' => "L",
+        'ñ' | 'ń' => "n",
+        'Ñ' | 'Ń' => "N",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ō' => "o",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ō' => "O",
+        'š' | 'ś' => "s",
+        'Š' | 'Ś' => "S",
+        'þ' => "th",
+        'Þ' => "Th",
+        'ù' | 'ú' | 'û' | 'ū' => "u",
+        'Ù' | 'Ú' | 'Û' | 'Ū' => "U",
+        'ý' | 'ÿ' => "y",
+        'Ý' => "Y",
+        'ž' | 'ź' | 'ż' => "z",
+        'Ž' | 'Ź' | 'Ż' => "Z",
+        _ => return None,
+    };
+    Some(replacement)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FilenameSanitizer;
+
+    #[test]
+    fn sanitize_default() {
+        let sanitizer = FilenameSanitizer::new();
+        assert_eq!(
+            sanitizer.sanitize("blåbærsyltetøy.jpg"),
+            "blaabaersyltetoey.jpg"
+        );
+        assert_eq!(sanitizer.sanitize("what?#100%.txt"), "what__100_.txt");
+        assert_eq!(sanitizer.sanitize(".hidden"), ".hidden");
+        assert_eq!(sanitizer.sanitize("日本.txt"), "日本.txt");
+    }
+
+    #[test]
+    fn sanitize_without_transliteration() {
+        let sanitizer = FilenameSanitizer::new().transliterate(false);
+        assert_eq!(sanitizer.sanitize("blåbær?.jpg"), "blåbær_.jpg");
+    }
+
+    #[test]
+    fn sanitize_slugify() {
+        let sanitizer = FilenameSanitizer::new().slugify(true).lowercase(true);
+        assert_eq!(
+            sanitizer.sanitize("  Møte -- referat 2020 (utkast).DOCX"),
+            "moete-referat-2020-utkast.docx"
+        );
+        assert_eq!(sanitizer.sanitize("日本.txt"), "file.txt");
+    }
+
+    #[test]
+    fn sanitize_max_length() {
+        let sanitizer = FilenameSanitizer::new().max_length(Some(10));
+        assert_eq!(sanitizer.sanitize("a-very-long-name.pdf"), "a-very.pdf");
+
+        let sanitizer = sanitizer.preserve_extension(false);
+        assert_eq!(sanitizer.sanitize("a-very-long-name.pdf"), "a-very-lon");
+    }
+
+    #[test]
+    fn sanitize_max_length_with_long_extension() {
+        let sanitizer = FilenameSanitizer::new().max_length(Some(4));
+        assert_eq!(sanitizer.sanitize("report.backup"), "repo");
+        assert_eq!(sanitizer.sanitize("report.pdf"), "repo");
+        assert_eq!(sanitizer.sanitize("report.gz"), "r.gz");
+
+        let sanitizer = FilenameSanitizer::new().max_length(Some(0));
+        assert_eq!(sanitizer.sanitize("report.pdf"), "r");
+    }
+}
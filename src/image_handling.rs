@@ -2,9 +2,10 @@
 //! Module for working with images in smooth storage
 //!
 
-use crate::{AssClient, AssError, ImageData};
-use reqwest::multipart::Form;
-use reqwest::Url;
+use crate::request::Request;
+use crate::{Acl, AssClient, AssError, ImageData, UploadOptions};
+use chrono::{DateTime, Utc};
+use reqwest::{Method, Url};
 use std::path::PathBuf;
 
 /// Upload an image to smooth storage
@@ -12,20 +13,51 @@ pub async fn upload_image<T: Into<PathBuf>>(
     ass_client: &AssClient,
     path: T,
 ) -> Result<ImageData, AssError> {
-    let path = path.into();
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join("images")?;
+    upload_image_with_options(ass_client, path, &UploadOptions::new()).await
+}
 
+/// Upload an image to smooth storage with the given acl, overriding the client's default acl
+pub async fn upload_image_with_acl<T: Into<PathBuf>>(
+    ass_client: &AssClient,
+    path: T,
+    acl: Acl,
+) -> Result<ImageData, AssError> {
+    upload_image_with_options(ass_client, path, &UploadOptions::new().acl(acl)).await
+}
+
+/// Upload an image to smooth storage with the given upload options
+pub async fn upload_image_with_options<T: Into<PathBuf>>(
+    ass_client: &AssClient,
+    path: T,
+    options: &UploadOptions,
+) -> Result<ImageData, AssError> {
+    let path = path.into();
     let stream = std::fs::read(path)?;
-    let form = Form::new().part("file", reqwest::multipart::Part::stream(stream));
+    let length = stream.len();
+    let form = options.to_form(stream)?;
 
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
+    Request::api(ass_client, "upload_image", Method::POST, "images")?
+        .headers(options.to_headers()?)
+        .multipart(form, length)
+        .send_json()
+        .await
+}
 
-    let res = client.post(url).multipart(form).send().await?;
-    let data: ImageData = res.json().await?;
-    Ok(data)
+/// Change the acl of an existing image
+pub async fn set_image_acl(
+    ass_client: &AssClient,
+    image_id: u64,
+    acl: Acl,
+) -> Result<ImageData, AssError> {
+    Request::api(
+        ass_client,
+        "set_image_acl",
+        Method::PUT,
+        &format!("images/{}/acl", image_id),
+    )?
+    .json(&serde_json::json!({ "acl": acl }))?
+    .send_json()
+    .await
 }
 
 /// Fetch image information
@@ -33,21 +65,53 @@ pub async fn get_image_information(
     ass_client: &AssClient,
     image_id: u64,
 ) -> Result<ImageData, AssError> {
-    let url = Url::parse(&ass_client.url_string())?;
-    let url = url.join(&format!("images/{}", image_id))?;
+    Request::api(
+        ass_client,
+        "get_image_information",
+        Method::GET,
+        &format!("images/{}", image_id),
+    )?
+    .send_json()
+    .await
+}
+
+/// Download the original content of an image, through a signed url
+pub async fn download_image(ass_client: &AssClient, image_id: u64) -> Result<Vec<u8>, AssError> {
+    let url = get_image_url(ass_client, image_id)?;
+    Request::signed(ass_client, "download_image", &url)?
+        .send_bytes()
+        .await
+}
 
-    let client = reqwest::Client::builder()
-        .default_headers(ass_client.get_headers()?)
-        .build()?;
-    let res = client.get(url).send().await?;
-    let data: ImageData = res.json().await?;
-    Ok(data)
+/// Delete an image
+pub async fn delete_image(ass_client: &AssClient, image_id: u64) -> Result<(), AssError> {
+    Request::api(
+        ass_client,
+        "delete_image",
+        Method::DELETE,
+        &format!("images/{}", image_id),
+    )?
+    .send()
+    .await?;
+    Ok(())
 }
 
 /// Get signed image url based on image id
 pub fn get_image_url(ass_client: &AssClient, id: u64) -> Result<String, AssError> {
     let url = Url::parse(&ass_client.url_string())?;
     let url = url.join(&format!("users/{}/images/{}.jpg", ass_client.name, id))?;
-    let url = ass_client.sign_url(&url.as_str())?;
+    let url = ass_client.sign_url(url.as_str())?;
+    Ok(url.to_string())
+}
+
+/// Get signed image url based on image id, valid until `expires`
+pub fn get_image_url_expiring(
+    ass_client: &AssClient,
+    id: u64,
+    expires: DateTime<Utc>,
+) -> Result<String, AssError> {
+    let url = Url::parse(&ass_client.url_string())?;
+    let url = url.join(&format!("users/{}/images/{}.jpg", ass_client.name, id))?;
+    let url = ass_client.sign_url_expiring(url.as_str(), expires)?;
     Ok(url.to_string())
 }
@@ -0,0 +1,101 @@
+//!
+//! Reads image dimensions from file headers, for storage backends that handle image data
+//! locally
+//!
+
+/// Image formats recognized by `image_info`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ImageFormat {
+    Png,
+    Gif,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// File extension used for images of this format
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Detect the format and dimensions, as `(width, height)`, of PNG, GIF and JPEG images
+pub(crate) fn image_info(data: &[u8]) -> Option<(ImageFormat, u64, u64)> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.len() >= 24 && &data[12..16] == b"IHDR" {
+        let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+        let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+        return Some((ImageFormat::Png, width.into(), height.into()));
+    }
+    if (data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")) && data.len() >= 10 {
+        let width = u16::from_le_bytes([data[6], data[7]]);
+        let height = u16::from_le_bytes([data[8], data[9]]);
+        return Some((ImageFormat::Gif, width.into(), height.into()));
+    }
+    if data.starts_with(&[0xff, 0xd8]) {
+        return jpeg_dimensions(data).map(|(w, h)| (ImageFormat::Jpeg, w, h));
+    }
+    None
+}
+
+/// Walk the JPEG segments until a start-of-frame marker holding the dimensions is found
+fn jpeg_dimensions(data: &[u8]) -> Option<(u64, u64)> {
+    let mut i = 2;
+    while i + 4 <= data.len() {
+        if data[i] != 0xff {
+            return None;
+        }
+        let marker = data[i + 1];
+        if marker == 0xff {
+            i += 1;
+            continue;
+        }
+        let length = usize::from(u16::from_be_bytes([data[i + 2], data[i + 3]]));
+        let is_start_of_frame =
+            (0xc0..=0xcf).contains(&marker) && ![0xc4, 0xc8, 0xcc].contains(&marker);
+        if is_start_of_frame {
+            if i + 9 > data.len() {
+                return None;
+            }
+            let height = u16::from_be_bytes([data[i + 5], data[i + 6]]);
+            let width = u16::from_be_bytes([data[i + 7], data[i + 8]]);
+            return Some((width.into(), height.into()));
+        }
+        i += 2 + length;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{image_info, ImageFormat};
+
+    #[test]
+    fn png_dimensions() {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 1, 0x2c, 0, 0, 0, 0xc8]);
+        assert_eq!(image_info(&png), Some((ImageFormat::Png, 300, 200)));
+    }
+
+    #[test]
+    fn gif_dimensions() {
+        let gif = b"GIF89a\x40\x01\xf0\x00";
+        assert_eq!(image_info(gif), Some((ImageFormat::Gif, 320, 240)));
+    }
+
+    #[test]
+    fn jpeg_dimensions() {
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x01,
+            0xe0, 0x02, 0x80,
+        ];
+        assert_eq!(image_info(&jpeg), Some((ImageFormat::Jpeg, 640, 480)));
+    }
+
+    #[test]
+    fn unknown_format() {
+        assert_eq!(image_info(b"not an image"), None);
+    }
+}
@@ -3,30 +3,69 @@
 //! A library for working with Aptoma Smooth Storage.
 //!
 //! # Examples
-//! ```ignore
+//! ```no_run
 //! use ass_rs::{AssClient, AssError, file_handling, image_handling};
 //!
-//! # fn main() -> Result<(), AssError> {
-//!
+//! # async fn run() -> Result<(), AssError> {
 //! let ass_client = AssClient::create("https://url-to-storage", "account-name", "secretkey")?;
 //!
 //! let image_url = image_handling::get_image_url(&ass_client, 123)?;
-//! let image_data = image_handling::get_image_data(&ass_client, 123)?;
-//!
-//! let file_data = file_handling::upload_file(&ass_client, "/data/file.pdf", "/destination")?;
-//! let image_data = file_handling::upload_image(&ass_client, "/data/image.jpg")?;
+//! let image_data = image_handling::get_image_information(&ass_client, 123).await?;
 //!
+//! let file_data = file_handling::upload_file(&ass_client, "/data/file.pdf", "destination").await?;
+//! let image_data = image_handling::upload_image(&ass_client, "/data/image.jpg").await?;
 //! # Ok(())
 //! # }
+//! ```
+//!
+//! With the `blocking` feature enabled, the `blocking` module offers the same functions for
+//! synchronous code.
+//!
+//! ```ignore
+//! use ass_rs::{AssClient, AssError, blocking};
+//!
+//! let ass_client = AssClient::create("https://url-to-storage", "account-name", "secretkey")?;
 //!
+//! let image_data = blocking::image_handling::get_image_information(&ass_client, 123)?;
+//! let file_data = blocking::file_handling::upload_file(&ass_client, "/data/file.pdf", "destination")?;
 //! ```
 
+mod acl;
+mod ass_path;
+mod ass_url;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 mod client;
 mod data;
 mod error;
 pub mod file_handling;
+mod filename;
 pub mod image_handling;
+#[cfg(any(feature = "local", feature = "testing"))]
+mod image_size;
+mod limits;
+mod metrics;
+mod middleware;
+mod profiles;
+mod request;
+mod secret;
+mod storage;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod upload_options;
 
-pub use crate::client::AssClient;
+pub use crate::acl::Acl;
+pub use crate::ass_path::AssPath;
+pub use crate::ass_url::{AssResource, AssUrl};
+pub use crate::client::{AssClient, VerifiedUrl};
 pub use crate::data::{FileData, ImageData};
-pub use crate::error::{AssError, AssErrorKind};
+pub use crate::error::{AssError, AssErrorKind, ErrorContext};
+pub use crate::filename::FilenameSanitizer;
+pub use crate::metrics::{Metrics, RequestMetrics};
+pub use crate::middleware::{IncomingResponse, Middleware, OutgoingRequest};
+pub use crate::profiles::Profiles;
+pub use crate::secret::Secret;
+#[cfg(feature = "local")]
+pub use crate::storage::LocalStorage;
+pub use crate::storage::SmoothStorage;
+pub use crate::upload_options::{CacheControl, CollisionPolicy, ContentDisposition, UploadOptions};
@@ -0,0 +1,135 @@
+//!
+//! Client side limits on the rate and concurrency of requests
+//!
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Spaces requests evenly, allowing a given number of requests per second
+#[derive(Debug)]
+struct RateLimit {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimit {
+    /// Reserve the next free slot, returning when it starts
+    fn reserve(&self) -> Instant {
+        let mut next_slot = self.next_slot.lock().expect("Rate limit lock poisoned");
+        let slot = std::cmp::max(*next_slot, Instant::now());
+        *next_slot = slot + self.interval;
+        slot
+    }
+}
+
+/// The limits of a client, shared by all of its clones
+#[derive(Clone, Default)]
+pub(crate) struct Limits {
+    rate: Option<Arc<RateLimit>>,
+    in_flight: Option<(usize, Arc<Semaphore>)>,
+}
+
+impl Limits {
+    /// Allow at most `requests_per_second` requests to start each second, at least 1
+    pub(crate) fn set_rate(&mut self, requests_per_second: u32) {
+        let interval = Duration::from_secs(1) / requests_per_second.max(1);
+        self.rate = Some(Arc::new(RateLimit {
+            interval,
+            next_slot: Mutex::new(Instant::now()),
+        }));
+    }
+
+    /// Allow at most `max` requests in flight at the same time, at least 1
+    pub(crate) fn set_max_in_flight(&mut self, max: usize) {
+        let max = max.max(1);
+        self.in_flight = Some((max, Arc::new(Semaphore::new(max))));
+    }
+
+    /// Wait until a request may be sent. The request counts as in flight until the returned
+    /// permit is dropped
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let permit = match &self.in_flight {
+            Some((_, semaphore)) => Some(semaphore.acquire().await),
+            None => None,
+        };
+        if let Some(rate) = &self.rate {
+            let slot = rate.reserve();
+            if slot > Instant::now() {
+                tokio::time::delay_until(slot.into()).await;
+            }
+        }
+        permit
+    }
+}
+
+impl Debug for Limits {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        fmt.debug_struct("Limits")
+            .field("interval", &self.rate.as_ref().map(|rate| rate.interval))
+            .field(
+                "max_in_flight",
+                &self.in_flight.as_ref().map(|(max, _)| max),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::limits::Limits;
+    use std::time::{Duration, Instant};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    #[test]
+    fn rate_limit_spaces_requests() {
+        let mut limits = Limits::default();
+        limits.set_rate(20);
+        let shared = limits.clone();
+
+        let started = Instant::now();
+        aw!(async {
+            for _ in 0..3 {
+                limits.acquire().await;
+                shared.acquire().await;
+            }
+        });
+        assert!(started.elapsed() >= Duration::from_millis(250));
+    }
+
+    #[test]
+    fn max_in_flight() {
+        let mut limits = Limits::default();
+        limits.set_max_in_flight(2);
+        let shared = limits.clone();
+
+        aw!(async {
+            let first = limits.acquire().await;
+            let _second = shared.acquire().await;
+            assert!(first.is_some());
+            let third = tokio::time::timeout(Duration::from_millis(50), limits.acquire()).await;
+            assert!(third.is_err());
+            drop(first);
+            let third = tokio::time::timeout(Duration::from_millis(50), shared.acquire()).await;
+            assert!(third.is_ok());
+        });
+    }
+
+    #[test]
+    fn zero_limits_allow_one_request() {
+        let mut limits = Limits::default();
+        limits.set_rate(0);
+        limits.set_max_in_flight(0);
+
+        aw!(async {
+            let first = tokio::time::timeout(Duration::from_millis(50), limits.acquire()).await;
+            assert!(first.expect("First request should not wait").is_some());
+        });
+    }
+}
@@ -0,0 +1,165 @@
+//!
+//! Reporting measurements of requests made to smooth storage
+//!
+
+use crate::AssError;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+
+///
+/// Measurements of a finished request to smooth storage.
+///
+/// `operation` names the library function making the request, such as `upload_file` or
+/// `get_image_information`.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct RequestMetrics<'a> {
+    /// The operation making the request
+    pub operation: &'a str,
+    /// The response status, if a response was received
+    pub status: Option<u16>,
+    /// The error the request failed with, if any
+    pub error: Option<&'a AssError>,
+    /// Time from sending the request until the response body was read
+    pub duration: Duration,
+    /// Size of the request body
+    pub bytes_sent: u64,
+    /// Size of the response body
+    pub bytes_received: u64,
+}
+
+///
+/// Receives measurements of every request made with a client.
+///
+/// Implement this to feed request counts, error kinds, latencies and transferred bytes into a
+/// metrics system, and register it with `AssClient::with_metrics`.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{AssClient, Metrics, RequestMetrics};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// #[derive(Default)]
+/// struct UploadedBytes(AtomicU64);
+///
+/// impl Metrics for UploadedBytes {
+///     fn record_request(&self, request: &RequestMetrics) {
+///         self.0.fetch_add(request.bytes_sent, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let uploaded = Arc::new(UploadedBytes::default());
+/// let ass_client = AssClient::create("https://storage.url.com", "username", "password")?
+///     .with_metrics(uploaded.clone());
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+pub trait Metrics: Send + Sync {
+    /// Called once for every finished request, whether it succeeded or failed
+    fn record_request(&self, request: &RequestMetrics);
+}
+
+/// A shared metrics recorder, held by the client
+#[derive(Clone)]
+pub(crate) struct MetricsRecorder(pub(crate) Arc<dyn Metrics>);
+
+impl Debug for MetricsRecorder {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "MetricsRecorder")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{file_handling, AssClient, Metrics, RequestMetrics};
+    use std::sync::{Arc, Mutex};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct Recorded {
+        operation: String,
+        status: Option<u16>,
+        error: Option<String>,
+        bytes_sent: u64,
+        bytes_received: u64,
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Recorded>>);
+
+    impl Metrics for Recorder {
+        fn record_request(&self, request: &RequestMetrics) {
+            self.0.lock().unwrap().push(Recorded {
+                operation: request.operation.to_string(),
+                status: request.status,
+                error: request.error.map(|err| err.kind.to_string()),
+                bytes_sent: request.bytes_sent,
+                bytes_received: request.bytes_received,
+            });
+        }
+    }
+
+    #[test]
+    fn record_requests() {
+        let body = "{\"id\": 2, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}";
+        let _m = mockito::mock("POST", "/files/metrics/account.json")
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create();
+        let _m = mockito::mock("GET", "/files/404404")
+            .with_status(404)
+            .create();
+
+        let recorder = Arc::new(Recorder::default());
+        let ass_client = AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient")
+            .with_metrics(recorder.clone());
+
+        aw!(file_handling::upload_file(
+            &ass_client.clone(),
+            "./data/account.json",
+            "metrics/"
+        ))
+        .expect("Could not upload file");
+        assert!(aw!(file_handling::get_file_information_by_id(
+            &ass_client,
+            404404
+        ))
+        .is_err());
+
+        let content_length = std::fs::metadata("./data/account.json")
+            .expect("Could not read file")
+            .len();
+        let recorded = recorder.0.lock().unwrap();
+        assert_eq!(
+            *recorded,
+            vec![
+                Recorded {
+                    operation: "upload_file".to_string(),
+                    status: Some(201),
+                    error: None,
+                    bytes_sent: content_length,
+                    bytes_received: body.len() as u64,
+                },
+                Recorded {
+                    operation: "get_file_information_by_id".to_string(),
+                    status: Some(404),
+                    error: Some("Reqwest Error".to_string()),
+                    bytes_sent: 0,
+                    bytes_received: 0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//!
+//! Hooks run around every request made to smooth storage
+//!
+
+use crate::AssError;
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// A request about to be sent, with headers that middleware can change
+#[derive(Debug)]
+pub struct OutgoingRequest<'a> {
+    /// The operation making the request, such as `upload_file`
+    pub operation: &'a str,
+    /// The request method
+    pub method: &'a Method,
+    /// The request url, with access tokens redacted
+    pub url: &'a str,
+    /// Headers sent along with the client's authentication headers
+    pub headers: &'a mut HeaderMap,
+}
+
+/// A response received from smooth storage, before its status is checked
+#[derive(Debug)]
+pub struct IncomingResponse<'a> {
+    /// The operation that made the request
+    pub operation: &'a str,
+    /// The request method
+    pub method: &'a Method,
+    /// The request url, with access tokens redacted
+    pub url: &'a str,
+    /// The response status
+    pub status: StatusCode,
+    /// The response headers
+    pub headers: &'a HeaderMap,
+}
+
+///
+/// Middleware run around every request made with a client.
+///
+/// `on_request` can add or change headers before a request is sent, or fail it by returning an
+/// error. `on_response` sees every response, including error responses. Middleware registered
+/// with `AssClient::with_middleware` runs in registration order for requests, and in reverse
+/// order for responses.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{AssClient, Middleware, OutgoingRequest};
+/// use std::sync::Arc;
+///
+/// struct CorrelationId(String);
+///
+/// impl Middleware for CorrelationId {
+///     fn on_request(&self, request: &mut OutgoingRequest) -> Result<(), AssError> {
+///         request.headers.insert("x-correlation-id", self.0.parse()?);
+///         Ok(())
+///     }
+/// }
+///
+/// let ass_client = AssClient::create("https://storage.url.com", "username", "password")?
+///     .with_middleware(Arc::new(CorrelationId("batch-17".to_string())));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+pub trait Middleware: Send + Sync {
+    /// Inspect or change a request before it is sent
+    fn on_request(&self, _request: &mut OutgoingRequest) -> Result<(), AssError> {
+        Ok(())
+    }
+
+    /// Observe a response
+    fn on_response(&self, _response: &IncomingResponse) {}
+}
+
+/// The middleware registered on a client, in registration order
+#[derive(Clone, Default)]
+pub(crate) struct MiddlewareStack(Vec<Arc<dyn Middleware>>);
+
+impl MiddlewareStack {
+    pub(crate) fn push(&mut self, middleware: Arc<dyn Middleware>) {
+        self.0.push(middleware);
+    }
+
+    pub(crate) fn on_request(&self, request: &mut OutgoingRequest) -> Result<(), AssError> {
+        self.0
+            .iter()
+            .try_for_each(|middleware| middleware.on_request(request))
+    }
+
+    pub(crate) fn on_response(&self, response: &IncomingResponse) {
+        for middleware in self.0.iter().rev() {
+            middleware.on_response(response);
+        }
+    }
+}
+
+impl Debug for MiddlewareStack {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "MiddlewareStack({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        file_handling, AssClient, AssError, IncomingResponse, Middleware, OutgoingRequest,
+    };
+    use reqwest::header::HeaderName;
+    use std::sync::{Arc, Mutex};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    /// Adds a header to requests, and records the responses it sees
+    struct Audit {
+        name: &'static str,
+        seen: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Middleware for Audit {
+        fn on_request(&self, request: &mut OutgoingRequest) -> Result<(), AssError> {
+            let header = format!("x-audit-{}", self.name);
+            request
+                .headers
+                .insert(HeaderName::from_bytes(header.as_bytes())?, "yes".parse()?);
+            Ok(())
+        }
+
+        fn on_response(&self, response: &IncomingResponse) {
+            self.seen.lock().unwrap().push(format!(
+                "{} {} {}",
+                self.name, response.operation, response.status
+            ));
+        }
+    }
+
+    /// Records the debug output of the requests and responses it sees
+    struct Log(Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Log {
+        fn on_request(&self, request: &mut OutgoingRequest) -> Result<(), AssError> {
+            self.0.lock().unwrap().push(format!("{:?}", request));
+            Ok(())
+        }
+
+        fn on_response(&self, response: &IncomingResponse) {
+            self.0.lock().unwrap().push(format!("{:?}", response));
+        }
+    }
+
+    /// Fails requests with an error from an earlier request
+    struct Fail(Mutex<Option<AssError>>);
+
+    impl Middleware for Fail {
+        fn on_request(&self, _request: &mut OutgoingRequest) -> Result<(), AssError> {
+            Err(self
+                .0
+                .lock()
+                .unwrap()
+                .take()
+                .expect("Only one request expected"))
+        }
+    }
+
+    struct Deny;
+
+    impl Middleware for Deny {
+        fn on_request(&self, request: &mut OutgoingRequest) -> Result<(), AssError> {
+            Err(AssError::url_does_not_match_account(
+                request.url.to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    fn middleware_changes_requests_and_observes_responses() {
+        let _m = mockito::mock("DELETE", "/files/31337")
+            .match_header("Authorization", "bearer apikey")
+            .match_header("x-audit-first", "yes")
+            .match_header("x-audit-second", "yes")
+            .with_status(204)
+            .create();
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let ass_client = AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient")
+            .with_middleware(Arc::new(Audit {
+                name: "first",
+                seen: seen.clone(),
+            }))
+            .with_middleware(Arc::new(Audit {
+                name: "second",
+                seen: seen.clone(),
+            }));
+
+        aw!(file_handling::delete_file(&ass_client, 31337)).expect("Could not delete file");
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "second delete_file 204 No Content",
+                "first delete_file 204 No Content"
+            ]
+        );
+    }
+
+    #[test]
+    fn middleware_can_fail_requests() {
+        let m = mockito::mock("DELETE", "/files/31338").expect(0).create();
+
+        let ass_client = AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient")
+            .with_middleware(Arc::new(Deny));
+
+        let err = aw!(file_handling::delete_file(&ass_client, 31338))
+            .expect_err("Request should be denied");
+        assert_eq!(
+            err.context().map(|context| context.operation.as_str()),
+            Some("delete_file")
+        );
+        m.assert();
+    }
+
+    #[test]
+    fn middleware_sees_redacted_urls() {
+        let _m = mockito::mock("GET", "/users/account/files/middleware/a.txt")
+            .match_query(mockito::Matcher::Regex("accessToken=".to_string()))
+            .with_status(200)
+            .with_body("content")
+            .create();
+
+        let log = Arc::new(Mutex::new(vec![]));
+        let ass_client = AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient")
+            .with_middleware(Arc::new(Log(log.clone())));
+        let signed_url = file_handling::get_file_url(&ass_client, "middleware/a.txt")
+            .expect("Could not sign url");
+        let token = signed_url
+            .split("accessToken=")
+            .nth(1)
+            .expect("Url should be signed");
+
+        aw!(file_handling::download_file(
+            &ass_client,
+            "middleware/a.txt"
+        ))
+        .expect("Could not download file");
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 2);
+        for entry in log.iter() {
+            assert!(entry.contains("accessToken=[redacted]"));
+            assert!(!entry.contains(token));
+        }
+    }
+
+    #[test]
+    fn middleware_errors_keep_their_context() {
+        let _m = mockito::mock("GET", "/files/31339")
+            .with_status(500)
+            .create();
+
+        let ass_client = AssClient::create(mockito::server_url(), "account", "apikey")
+            .expect("Could not get AssClient");
+        let err = aw!(file_handling::get_file_information_by_id(
+            &ass_client,
+            31339
+        ))
+        .expect_err("Request should fail");
+
+        let ass_client = ass_client.with_middleware(Arc::new(Fail(Mutex::new(Some(err)))));
+        let err = aw!(file_handling::delete_file(&ass_client, 31339))
+            .expect_err("Request should be failed by middleware");
+        assert_eq!(
+            err.context().map(|context| context.operation.as_str()),
+            Some("get_file_information_by_id")
+        );
+    }
+}
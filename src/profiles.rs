@@ -0,0 +1,244 @@
+use crate::{AssClient, AssError};
+use reqwest::Url;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Environment variable holding the path of the profiles file
+pub(crate) const CONFIG_VAR: &str = "ASS_CONFIG";
+/// Environment variable selecting the profile used by `AssClient::from_default_profile`
+pub(crate) const PROFILE_VAR: &str = "ASS_PROFILE";
+/// Environment variable overriding the url of the loaded profile
+pub(crate) const URL_VAR: &str = "ASS_URL";
+/// Environment variable overriding the account name of the loaded profile
+pub(crate) const NAME_VAR: &str = "ASS_NAME";
+/// Environment variable overriding the api key of the loaded profile
+pub(crate) const APIKEY_VAR: &str = "ASS_APIKEY";
+
+const CONFIG_DIR: &str = "ass";
+const CONFIG_FILES: [&str; 2] = ["config.toml", "config.json"];
+
+///
+/// Named account profiles, loaded from a TOML or JSON file.
+///
+/// Each profile has the same fields as an account file, and `default` names the profile used
+/// when none is given:
+///
+/// ```toml
+/// default = "prod"
+///
+/// [profiles.prod]
+/// url = "https://storage.url.com"
+/// name = "account"
+/// apikey = "secret"
+///
+/// [profiles.staging]
+/// url = "https://staging.storage.url.com"
+/// name = "account"
+/// apikey = "secret"
+/// acl = "private"
+/// ```
+///
+/// The file is found through the `ASS_CONFIG` environment variable, or as `ass/config.toml` or
+/// `ass/config.json` in the user's configuration directory, `$XDG_CONFIG_HOME` or
+/// `~/.config` on unix and `%APPDATA%` on windows. The `ASS_URL`, `ASS_NAME` and `ASS_APIKEY`
+/// environment variables override the values of the loaded profile.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::Profiles;
+///
+/// let profiles = Profiles::load("./data/profiles.toml")?;
+/// let ass_client = profiles.client("staging")?;
+/// assert_eq!(profiles.default_profile(), Some("prod"));
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Deserialize)]
+pub struct Profiles {
+    default: Option<String>,
+    #[serde(default)]
+    profiles: BTreeMap<String, AssClient>,
+}
+
+impl Profiles {
+    /// Load profiles from a file, parsed as TOML if the extension is `.toml` and JSON otherwise
+    pub fn load<T: Into<PathBuf>>(path: T) -> Result<Self, AssError> {
+        let path = path.into();
+        let contents = std::fs::read_to_string(&path)?;
+        let is_toml = path.extension().map(|e| e == "toml").unwrap_or(false);
+        let profiles = if is_toml {
+            toml::from_str(&contents).map_err(|err| err.to_string())
+        } else {
+            serde_json::from_str(&contents).map_err(|err| err.to_string())
+        };
+        let profiles: Profiles = profiles
+            .map_err(|err| AssError::invalid_account_file(err, path.display().to_string()))?;
+        for (name, client) in &profiles.profiles {
+            if let Err(err) = client.configured_url().parse::<Url>() {
+                return Err(AssError::invalid_account_file(
+                    format!("profile {} has an invalid url: {}", name, err),
+                    path.display().to_string(),
+                ));
+            }
+        }
+        Ok(profiles)
+    }
+
+    /// Load profiles from the standard location
+    pub fn discover() -> Result<Self, AssError> {
+        match Profiles::config_path() {
+            Some(path) => Profiles::load(path),
+            None => Err(AssError::config_not_found()),
+        }
+    }
+
+    /// Find the profiles file, from `ASS_CONFIG` or in the user's configuration directory
+    pub fn config_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_VAR) {
+            return Some(PathBuf::from(path));
+        }
+        let config_dir = config_dir()?.join(CONFIG_DIR);
+        CONFIG_FILES
+            .iter()
+            .map(|file| config_dir.join(file))
+            .find(|path| path.is_file())
+    }
+
+    /// The name of the default profile, if any
+    pub fn default_profile(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// The names of all profiles
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(|name| name.as_str())
+    }
+
+    /// Create a client for the named profile, with environment overrides applied
+    pub fn client(&self, profile: &str) -> Result<AssClient, AssError> {
+        self.client_with_overrides(profile, |var| std::env::var(var).ok())
+    }
+
+    /// Create a client for the profile named by `ASS_PROFILE`, or the default profile
+    pub fn default_client(&self) -> Result<AssClient, AssError> {
+        let profile = std::env::var(PROFILE_VAR)
+            .ok()
+            .or_else(|| self.default.clone())
+            .ok_or_else(|| AssError::profile_not_found("default".to_string()))?;
+        self.client(&profile)
+    }
+
+    fn client_with_overrides<F: Fn(&str) -> Option<String>>(
+        &self,
+        profile: &str,
+        env: F,
+    ) -> Result<AssClient, AssError> {
+        let client = self
+            .profiles
+            .get(profile)
+            .ok_or_else(|| AssError::profile_not_found(profile.to_string()))?;
+        let url = env(URL_VAR).unwrap_or_else(|| client.configured_url().to_string());
+        let name = env(NAME_VAR).unwrap_or_else(|| client.name.clone());
+        let apikey = env(APIKEY_VAR).unwrap_or_else(|| client.apikey().expose().to_string());
+        Ok(AssClient::create(url, name, apikey)?
+            .with_acl(client.acl())
+            .with_previous_apikeys(client.previous_apikeys()))
+    }
+}
+
+/// The user's configuration directory
+fn config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return std::env::var_os("APPDATA").map(PathBuf::from);
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profiles::{APIKEY_VAR, URL_VAR};
+    use crate::{Acl, AssErrorKind, Profiles};
+
+    #[test]
+    fn load_toml_profiles() {
+        let profiles = Profiles::load("./data/profiles.toml").expect("Could not load profiles");
+        assert_eq!(profiles.default_profile(), Some("prod"));
+        assert_eq!(
+            profiles.names().collect::<Vec<_>>(),
+            vec!["prod", "staging"]
+        );
+
+        let ass_client = profiles
+            .client_with_overrides("staging", |_| None)
+            .expect("Could not get client");
+        assert_eq!(ass_client.name, "staging-account");
+        assert_eq!(ass_client.acl(), Acl::Private);
+    }
+
+    #[test]
+    fn load_json_profiles() {
+        let profiles = Profiles::load("./data/profiles.json").expect("Could not load profiles");
+        assert_eq!(profiles.default_profile(), Some("prod"));
+
+        let ass_client = profiles
+            .client_with_overrides("prod", |_| None)
+            .expect("Could not get client");
+        assert_eq!(ass_client.url_string(), "https://storage.url.com/");
+        assert_eq!(ass_client.acl(), Acl::Public);
+    }
+
+    #[test]
+    fn invalid_profile_url() {
+        let path = std::env::temp_dir().join("ass-rs-invalid-profile-url.toml");
+        std::fs::write(
+            &path,
+            "[profiles.prod]\nurl = \"storage.url.com\"\nname = \"account\"\napikey = \"secret\"\n",
+        )
+        .expect("Could not write profiles");
+        match Profiles::load(&path) {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidAccountFile { .. })),
+            Ok(_) => panic!("Profile with an invalid url should not load"),
+        }
+
+        let profiles = Profiles::load("./data/profiles.toml").expect("Could not load profiles");
+        let result = profiles.client_with_overrides("prod", |var| match var {
+            URL_VAR => Some("storage.url.com".to_string()),
+            _ => None,
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn missing_profile() {
+        let profiles = Profiles::load("./data/profiles.toml").expect("Could not load profiles");
+        match profiles.client("customer") {
+            Err(e) => assert_eq!(
+                e.kind,
+                AssErrorKind::ProfileNotFound("customer".to_string())
+            ),
+            Ok(_) => panic!("Profile should not exist"),
+        }
+    }
+
+    #[test]
+    fn environment_overrides() {
+        let profiles = Profiles::load("./data/profiles.toml").expect("Could not load profiles");
+        let ass_client = profiles
+            .client_with_overrides("prod", |var| match var {
+                URL_VAR => Some("https://other.url.com".to_string()),
+                APIKEY_VAR => Some("other-key".to_string()),
+                _ => None,
+            })
+            .expect("Could not get client");
+        assert_eq!(ass_client.url_string(), "https://other.url.com/");
+        assert_eq!(ass_client.name, "account");
+        assert_eq!(ass_client.apikey().expose(), "other-key");
+    }
+}
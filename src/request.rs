@@ -0,0 +1,480 @@
+//!
+//! Sending requests to smooth storage, attaching the request details to any error
+//!
+
+use crate::{AssClient, AssError, ErrorContext, IncomingResponse, OutgoingRequest, RequestMetrics};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::multipart::Form;
+use reqwest::{Method, Response, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::time::{Duration, Instant};
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+/// Response header holding the server's id for the request
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Query parameters whose values are replaced in urls recorded in errors
+const REDACTED_PARAMS: [&str; 1] = ["accessToken"];
+/// Maximum number of characters of a response body recorded in errors
+const MAX_ERROR_BODY_LENGTH: usize = 512;
+
+enum Body {
+    Json(Vec<u8>),
+    Multipart(Form),
+}
+
+/// A request to smooth storage, named by the operation making it
+pub(crate) struct Request<'a> {
+    ass_client: &'a AssClient,
+    operation: &'static str,
+    method: Method,
+    url: Url,
+    authenticated: bool,
+    headers: HeaderMap,
+    body: Option<Body>,
+    body_length: Option<u64>,
+}
+
+impl<'a> Request<'a> {
+    /// Create an authenticated request to the api endpoint at `path`, relative to the client's
+    /// base url
+    pub(crate) fn api(
+        ass_client: &'a AssClient,
+        operation: &'static str,
+        method: Method,
+        path: &str,
+    ) -> Result<Self, AssError> {
+        let url = Url::parse(&ass_client.url_string())?.join(path)?;
+        Ok(Request {
+            ass_client,
+            operation,
+            method,
+            url,
+            authenticated: true,
+            headers: HeaderMap::new(),
+            body: None,
+            body_length: None,
+        })
+    }
+
+    /// Create an unauthenticated `GET` request for a signed url
+    pub(crate) fn signed(
+        ass_client: &'a AssClient,
+        operation: &'static str,
+        url: &str,
+    ) -> Result<Self, AssError> {
+        Ok(Request {
+            ass_client,
+            operation,
+            method: Method::GET,
+            url: Url::parse(url)?,
+            authenticated: false,
+            headers: HeaderMap::new(),
+            body: None,
+            body_length: None,
+        })
+    }
+
+    /// Add query parameters to the url
+    pub(crate) fn query(mut self, pairs: &[(&str, &str)]) -> Self {
+        if !pairs.is_empty() {
+            self.url.query_pairs_mut().extend_pairs(pairs);
+        }
+        self
+    }
+
+    /// Add headers to the request
+    pub(crate) fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    /// Send a value as the JSON body of the request
+    pub(crate) fn json<T: Serialize + ?Sized>(mut self, body: &T) -> Result<Self, AssError> {
+        let body = serde_json::to_vec(body)?;
+        self.body_length = Some(body.len() as u64);
+        self.body = Some(Body::Json(body));
+        Ok(self)
+    }
+
+    /// Send a multipart form as the body of the request, holding `length` bytes of content
+    pub(crate) fn multipart(mut self, form: Form, length: usize) -> Self {
+        self.body = Some(Body::Multipart(form));
+        self.body_length = Some(length as u64);
+        self
+    }
+
+    /// Send the request, failing on error statuses
+    pub(crate) async fn send(self) -> Result<Response, AssError> {
+        self.run(|response, _| async move {
+            let length = response.content_length().unwrap_or(0);
+            Ok((response, length))
+        })
+        .await
+    }
+
+    /// Send the request and parse the JSON response body
+    pub(crate) async fn send_json<T: DeserializeOwned>(self) -> Result<T, AssError> {
+        self.run(|response, context| async move {
+            let body = read_body(response, &context).await?;
+            let value = serde_json::from_slice(&body).map_err(|err| {
+                let body = truncate(&String::from_utf8_lossy(&body));
+                AssError::from(err).with_context(ErrorContext {
+                    body: Some(body),
+                    ..context
+                })
+            })?;
+            Ok((value, body.len() as u64))
+        })
+        .await
+    }
+
+    /// Send the request and read the response body
+    pub(crate) async fn send_bytes(self) -> Result<Vec<u8>, AssError> {
+        self.run(|response, context| async move {
+            let body = read_body(response, &context).await?;
+            let length = body.len() as u64;
+            Ok((body, length))
+        })
+        .await
+    }
+
+    /// Send the request and hand the response to `read`, which returns the result along with
+    /// the number of bytes received
+    async fn run<T, F, Fut>(self, read: F) -> Result<T, AssError>
+    where
+        F: FnOnce(Response, ErrorContext) -> Fut,
+        Fut: Future<Output = Result<(T, u64), AssError>>,
+    {
+        let ass_client = self.ass_client;
+        let operation = self.operation;
+        let bytes_sent = self.body_length.unwrap_or(0);
+        #[cfg(feature = "tracing")]
+        let span = self.span();
+        let _permit = ass_client.limits().acquire().await;
+        let started = Instant::now();
+
+        let result = async move {
+            let (response, context) = self.send_with_context().await?;
+            let status = response.status().as_u16();
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                status,
+                request_id = context.request_id.as_deref().unwrap_or(""),
+                "response received"
+            );
+            let (value, bytes_received) = read(response, context).await?;
+            Ok((value, status, bytes_received))
+        };
+        #[cfg(feature = "tracing")]
+        let result = result.instrument(span.clone());
+        let result: Result<(T, u16, u64), AssError> = result.await;
+        let elapsed = started.elapsed();
+
+        #[cfg(feature = "tracing")]
+        span.in_scope(|| trace_outcome(&result, elapsed));
+        if let Some(metrics) = ass_client.metrics() {
+            let (status, bytes_received) = match &result {
+                Ok((_, status, bytes_received)) => (Some(*status), *bytes_received),
+                Err(err) => (err.status(), 0),
+            };
+            metrics.record_request(&RequestMetrics {
+                operation,
+                status,
+                error: result.as_ref().err(),
+                duration: elapsed,
+                bytes_sent,
+                bytes_received,
+            });
+        }
+        result.map(|(value, _, _)| value)
+    }
+
+    /// The span covering the request, with access tokens redacted from the url. Requests are
+    /// sent once, without retries, so `attempt` is always 1
+    #[cfg(feature = "tracing")]
+    fn span(&self) -> tracing::Span {
+        tracing::info_span!(
+            "ass_request",
+            operation = self.operation,
+            attempt = 1u32,
+            method = %self.method,
+            resource = self.url.path(),
+            url = redact_url(&self.url).as_str(),
+            bytes_sent = self.body_length.unwrap_or(0),
+        )
+    }
+
+    async fn send_with_context(self) -> Result<(Response, ErrorContext), AssError> {
+        let mut context = ErrorContext {
+            operation: self.operation.to_string(),
+            method: self.method.to_string(),
+            url: redact_url(&self.url),
+            ..Default::default()
+        };
+        let default_headers = if self.authenticated {
+            self.ass_client
+                .get_headers()
+                .map_err(|err| err.with_context(context.clone()))?
+        } else {
+            HeaderMap::new()
+        };
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .build()
+            .map_err(|err| AssError::from(err).with_context(context.clone()))?;
+        let mut headers = self.headers;
+        let middleware = self.ass_client.middleware();
+        middleware
+            .on_request(&mut OutgoingRequest {
+                operation: self.operation,
+                method: &self.method,
+                url: &context.url,
+                headers: &mut headers,
+            })
+            .map_err(|err| match err.context() {
+                Some(_) => err,
+                None => err.with_context(context.clone()),
+            })?;
+        let mut builder = client
+            .request(self.method.clone(), self.url.clone())
+            .headers(headers);
+        builder = match self.body {
+            Some(Body::Json(body)) => builder.header(CONTENT_TYPE, "application/json").body(body),
+            Some(Body::Multipart(form)) => builder.multipart(form),
+            None => builder,
+        };
+
+        let response = builder
+            .send()
+            .await
+            .map_err(|err| AssError::from(err).with_context(context.clone()))?;
+        middleware.on_response(&IncomingResponse {
+            operation: self.operation,
+            method: &self.method,
+            url: &context.url,
+            status: response.status(),
+            headers: response.headers(),
+        });
+        context.status = Some(response.status().as_u16());
+        context.request_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        context.retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+
+        if let Err(err) = response.error_for_status_ref() {
+            let body = response.text().await.unwrap_or_default();
+            if !body.is_empty() {
+                context.body = Some(truncate(&body));
+            }
+            return Err(AssError::from(err).with_context(context));
+        }
+        Ok((response, context))
+    }
+}
+
+/// Read the whole response body
+async fn read_body(response: Response, context: &ErrorContext) -> Result<Vec<u8>, AssError> {
+    match response.bytes().await {
+        Ok(body) => Ok(body.to_vec()),
+        Err(err) => Err(AssError::from(err).with_context(context.clone())),
+    }
+}
+
+/// Emit the event finishing a request
+#[cfg(feature = "tracing")]
+fn trace_outcome<T>(result: &Result<(T, u16, u64), AssError>, elapsed: Duration) {
+    let duration_ms = elapsed.as_millis() as u64;
+    match result {
+        Ok((_, _, bytes_received)) => {
+            tracing::info!(bytes_received, duration_ms, "request completed");
+        }
+        Err(err) => {
+            tracing::warn!(
+                status = err.status().unwrap_or(0),
+                error = %err,
+                retryable = err.is_retryable(),
+                duration_ms,
+                "request failed"
+            );
+        }
+    }
+}
+
+/// The url with the values of access tokens replaced
+fn redact_url(url: &Url) -> String {
+    let mut redacted = url.clone();
+    if let Some(query) = url.query() {
+        let query: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.find('=') {
+                Some(i) if REDACTED_PARAMS.contains(&&pair[..i]) => {
+                    format!("{}=[redacted]", &pair[..i])
+                }
+                _ => pair.to_string(),
+            })
+            .collect();
+        redacted.set_query(Some(&query.join("&")));
+    }
+    redacted.to_string()
+}
+
+/// Parse a `Retry-After` header, given either as seconds or as an HTTP date
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let wait = date.with_timezone(&Utc) - now;
+    Some(wait.to_std().unwrap_or_else(|_| Duration::from_secs(0)))
+}
+
+/// Limit a response body to `MAX_ERROR_BODY_LENGTH` characters
+fn truncate(body: &str) -> String {
+    match body.char_indices().nth(MAX_ERROR_BODY_LENGTH) {
+        Some((i, _)) => format!("{}...", &body[..i]),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_retry_after, redact_url, truncate, MAX_ERROR_BODY_LENGTH};
+    use chrono::{TimeZone, Utc};
+    use reqwest::Url;
+    use std::time::Duration;
+
+    #[test]
+    fn redact_access_token() {
+        let url = Url::parse("http://url/users/name/files/a.txt?expires=10&accessToken=abcdef")
+            .expect("Could not parse url");
+        assert_eq!(
+            redact_url(&url),
+            "http://url/users/name/files/a.txt?expires=10&accessToken=[redacted]"
+        );
+    }
+
+    #[test]
+    fn truncate_body() {
+        assert_eq!(truncate("short"), "short");
+        let long = "æ".repeat(MAX_ERROR_BODY_LENGTH + 10);
+        assert_eq!(
+            truncate(&long).chars().count(),
+            MAX_ERROR_BODY_LENGTH + "...".len()
+        );
+    }
+
+    #[test]
+    fn retry_after() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::from_secs(0))
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[cfg(feature = "tracing")]
+    mod traced {
+        use crate::request::Request;
+        use crate::AssClient;
+        use std::fmt::Debug;
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        macro_rules! aw {
+            ($e:expr) => {
+                tokio_test::block_on($e)
+            };
+        }
+
+        /// Collects every span and event field as `name=value`
+        #[derive(Clone, Default)]
+        struct Collector(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Collector {
+            fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+                let mut fields = self.0.lock().unwrap();
+                fields.push(format!("{}={:?}", field.name(), value));
+            }
+        }
+
+        impl tracing::Subscriber for Collector {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes) -> Id {
+                span.record(&mut self.clone());
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, values: &Record) {
+                values.record(&mut self.clone());
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event) {
+                event.record(&mut self.clone());
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        #[test]
+        fn request_is_traced_without_secrets() {
+            let _m = mockito::mock("GET", "/users/account/files/a.txt")
+                .match_query(mockito::Matcher::Regex("accessToken=".to_string()))
+                .with_status(200)
+                .with_body("content")
+                .create();
+
+            let ass_client = AssClient::create(mockito::server_url(), "account", "secret-key")
+                .expect("Could not get AssClient");
+            let url = format!(
+                "{}/users/account/files/a.txt?accessToken=secret-token",
+                mockito::server_url()
+            );
+
+            let collector = Collector::default();
+            tracing::subscriber::with_default(collector.clone(), || {
+                let request = Request::signed(&ass_client, "download_file", &url)
+                    .expect("Could not create request");
+                aw!(request.send_bytes()).expect("Could not send request");
+            });
+
+            let fields = collector.0.lock().unwrap().join(" ");
+            assert!(fields.contains("operation=\"download_file\""));
+            assert!(fields.contains("attempt=1"));
+            assert!(fields.contains("resource=\"/users/account/files/a.txt\""));
+            assert!(fields.contains("accessToken=[redacted]"));
+            assert!(fields.contains("status=200"));
+            assert!(fields.contains("bytes_received=7"));
+            assert!(fields.contains("duration_ms="));
+            assert!(!fields.contains("secret-token"));
+            assert!(!fields.contains("secret-key"));
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Debug;
+use zeroize::Zeroize;
+
+/// Shown instead of the secret in debug and serialization output
+const REDACTED: &str = "[redacted]";
+
+///
+/// A secret value, such as an api key.
+///
+/// The value is redacted in `Debug` output and when serialized, and its memory is zeroed when
+/// it is dropped. Use `expose` to read it.
+///
+/// ```rust
+/// use ass_rs::Secret;
+///
+/// let secret = Secret::new("apikey");
+/// assert_eq!(format!("{:?}", secret), "Secret([redacted])");
+/// assert_eq!(secret.expose(), "apikey");
+/// ```
+///
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    /// Wrap a secret value
+    pub fn new<T: Into<String>>(secret: T) -> Self {
+        Secret(secret.into())
+    }
+
+    /// Read the secret value
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Secret(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Secret(secret.to_string())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(fmt, "Secret({})", REDACTED)
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AssClient, Secret};
+
+    #[test]
+    fn secret_is_redacted() {
+        let secret = Secret::new("apikey");
+        assert_eq!(format!("{:?}", secret), "Secret([redacted])");
+        assert_eq!(
+            serde_json::to_string(&secret).expect("Could not serialize secret"),
+            "\"[redacted]\""
+        );
+        let secret: Secret =
+            serde_json::from_str("\"apikey\"").expect("Could not deserialize secret");
+        assert_eq!(secret.expose(), "apikey");
+    }
+
+    #[test]
+    fn ass_client_does_not_leak_apikey() {
+        let ass_client =
+            AssClient::create("http://url", "name", "secret-key").expect("Could not get AssClient");
+        assert!(!format!("{:?}", ass_client).contains("secret-key"));
+        let json = serde_json::to_string(&ass_client).expect("Could not serialize AssClient");
+        assert!(!json.contains("secret-key"));
+    }
+}
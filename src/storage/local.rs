@@ -0,0 +1,562 @@
+use crate::file_handling::{prepare_file_name, rename_candidates};
+use crate::image_size::image_info;
+use crate::storage::matches_query;
+use crate::{
+    Acl, AssError, AssErrorKind, AssPath, CollisionPolicy, FileData, ImageData, SmoothStorage,
+    UploadOptions,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use url::Url;
+
+const INDEX_FILE: &str = "index.json";
+const FILES_DIR: &str = "files";
+const IMAGES_DIR: &str = "images";
+
+/// User id set on all files and images stored locally
+const LOCAL_USER_ID: u64 = 0;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Index {
+    next_file_id: u64,
+    next_image_id: u64,
+    files: BTreeMap<u64, IndexedFile>,
+    images: BTreeMap<u64, IndexedImage>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    file: FileData,
+    acl: Acl,
+    rendition: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct IndexedImage {
+    image: ImageData,
+    acl: Acl,
+    extension: String,
+}
+
+impl Default for Index {
+    fn default() -> Self {
+        Index {
+            next_file_id: 1,
+            next_image_id: 1,
+            files: BTreeMap::new(),
+            images: BTreeMap::new(),
+        }
+    }
+}
+
+impl Index {
+    fn find_file(&self, path: &AssPath) -> Option<&IndexedFile> {
+        self.files.values().find(|f| f.file.path == path.as_ref())
+    }
+}
+
+///
+/// Smooth storage backed by a local directory, for offline development.
+///
+/// Files are stored under `files/` and images under `images/` in the directory, and a JSON
+/// index, `index.json`, keeps the `FileData` and `ImageData` for them. Checksums, timestamps
+/// and image dimensions are computed like the storage cluster does, and urls point to the
+/// stored files with the `file` scheme.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{LocalStorage, SmoothStorage, UploadOptions};
+/// use std::path::Path;
+///
+/// let directory = std::env::temp_dir().join("ass-rs-local-doc");
+/// let storage = LocalStorage::open(&directory)?;
+///
+/// let file = tokio_test::block_on(storage.upload_file(
+///     Path::new("./data/account.json"),
+///     "documents",
+///     &UploadOptions::new(),
+/// ))?;
+/// assert_eq!(file.path, "documents/account.json");
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+pub struct LocalStorage {
+    root: PathBuf,
+    index: Mutex<Index>,
+}
+
+impl LocalStorage {
+    /// Open the storage in the given directory, creating it if needed
+    pub fn open<P: Into<PathBuf>>(root: P) -> Result<Self, AssError> {
+        let root = root.into();
+        std::fs::create_dir_all(root.join(FILES_DIR))?;
+        std::fs::create_dir_all(root.join(IMAGES_DIR))?;
+        let root = root.canonicalize()?;
+        let index_path = root.join(INDEX_FILE);
+        let index = if index_path.exists() {
+            serde_json::from_slice(&std::fs::read(&index_path)?)?
+        } else {
+            Index::default()
+        };
+        Ok(LocalStorage {
+            root,
+            index: Mutex::new(index),
+        })
+    }
+
+    /// The directory holding the storage
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Index> {
+        self.index.lock().expect("Local storage index poisoned")
+    }
+
+    /// Write an updated index, through a temporary file so a failed write does not corrupt it,
+    /// and replace the index in memory once it is written
+    fn save(&self, index: &mut Index, updated: Index) -> Result<(), AssError> {
+        let index_path = self.root.join(INDEX_FILE);
+        let temporary_path = self.root.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&temporary_path, serde_json::to_vec_pretty(&updated)?)?;
+        std::fs::rename(&temporary_path, &index_path)?;
+        *index = updated;
+        Ok(())
+    }
+
+    fn file_path(&self, path: &AssPath) -> PathBuf {
+        path.segments()
+            .fold(self.root.join(FILES_DIR), |local, segment| {
+                local.join(segment)
+            })
+    }
+
+    fn image_path(&self, image_id: u64, extension: &str) -> PathBuf {
+        self.root
+            .join(IMAGES_DIR)
+            .join(format!("{}.{}", image_id, extension))
+    }
+
+    fn file_url(&self, local_path: &Path) -> Result<String, AssError> {
+        Url::from_file_path(local_path)
+            .map(|url| url.to_string())
+            .map_err(|_| AssError::from(AssErrorKind::InvalidUrl))
+    }
+
+    /// Store image content and add it to the index, returning the id of the new image
+    fn store_image(
+        &self,
+        index: &mut Index,
+        content: &[u8],
+        name: &str,
+        acl: Acl,
+    ) -> Result<u64, AssError> {
+        let (format, width, height) =
+            image_info(content).ok_or_else(|| AssError::invalid_image(name.to_string()))?;
+        let id = index.next_image_id;
+        let local_path = self.image_path(id, format.extension());
+        std::fs::write(&local_path, content)?;
+
+        let now = Utc::now();
+        let image = ImageData {
+            id,
+            user_id: LOCAL_USER_ID,
+            md5: format!("{:x}", md5::compute(content)),
+            original_url: self.file_url(&local_path)?,
+            width,
+            height,
+            name: name.to_string(),
+            title: None,
+            description: None,
+            author: None,
+            source_url: None,
+            created: now,
+            updated: now,
+        };
+        index.next_image_id += 1;
+        index.images.insert(
+            id,
+            IndexedImage {
+                image,
+                acl,
+                extension: format.extension().to_string(),
+            },
+        );
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl SmoothStorage for LocalStorage {
+    async fn upload_file(
+        &self,
+        path: &Path,
+        destination: &str,
+        options: &UploadOptions,
+    ) -> Result<FileData, AssError> {
+        let destination = AssPath::parse(destination)?;
+        let (file_name, options) = prepare_file_name(path, options)?;
+        let mut index = self.lock();
+        let remote_path = match options.get_collision_policy() {
+            None | Some(CollisionPolicy::Overwrite) => destination.join(&file_name)?,
+            Some(CollisionPolicy::Fail) => {
+                let remote_path = destination.join(&file_name)?;
+                if index.find_file(&remote_path).is_some() {
+                    return Err(AssError::already_exists(remote_path.to_string()));
+                }
+                remote_path
+            }
+            Some(CollisionPolicy::Skip) => {
+                let remote_path = destination.join(&file_name)?;
+                if let Some(existing) = index.find_file(&remote_path) {
+                    return Ok(existing.file.clone());
+                }
+                remote_path
+            }
+            Some(CollisionPolicy::Rename) => {
+                let mut available = None;
+                for candidate in rename_candidates(&file_name) {
+                    let remote_path = destination.join(&candidate)?;
+                    if index.find_file(&remote_path).is_none() {
+                        available = Some(remote_path);
+                        break;
+                    }
+                }
+                available.ok_or_else(|| {
+                    AssError::already_exists(format!("{}/{}", destination, file_name))
+                })?
+            }
+        };
+
+        let content = std::fs::read(path)?;
+        let local_path = self.file_path(&remote_path);
+        if let Some(parent) = local_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&local_path, &content)?;
+
+        let now = Utc::now();
+        let mut updated = index.clone();
+        let (id, created) = match updated.find_file(&remote_path) {
+            Some(existing) => (existing.file.id, existing.file.created),
+            None => {
+                updated.next_file_id += 1;
+                (updated.next_file_id - 1, now)
+            }
+        };
+        let content_type = match options.get_content_type() {
+            Some(content_type) => content_type.to_string(),
+            None => mime_guess::from_path(&local_path)
+                .first_or_octet_stream()
+                .to_string(),
+        };
+        let file = FileData {
+            id,
+            user_id: LOCAL_USER_ID,
+            path: remote_path.to_string(),
+            md5: format!("{:x}", md5::compute(&content)),
+            content_type,
+            original_url: self.file_url(&local_path)?,
+            created,
+            updated: now,
+            metadata: options.get_metadata().clone().into_iter().collect(),
+        };
+        updated.files.insert(
+            id,
+            IndexedFile {
+                file: file.clone(),
+                acl: options.get_acl().unwrap_or_default(),
+                rendition: None,
+            },
+        );
+        self.save(&mut index, updated)?;
+        Ok(file)
+    }
+
+    async fn upload_image(
+        &self,
+        path: &Path,
+        options: &UploadOptions,
+    ) -> Result<ImageData, AssError> {
+        let content = std::fs::read(path)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut index = self.lock();
+        let mut updated = index.clone();
+        let id = self.store_image(
+            &mut updated,
+            &content,
+            &name,
+            options.get_acl().unwrap_or_default(),
+        )?;
+        self.save(&mut index, updated)?;
+        Ok(index.images[&id].image.clone())
+    }
+
+    async fn get_file_information_by_id(&self, id: u64) -> Result<FileData, AssError> {
+        self.lock()
+            .files
+            .get(&id)
+            .map(|f| f.file.clone())
+            .ok_or_else(|| AssError::not_found(format!("files/{}", id)))
+    }
+
+    async fn get_file_information_by_path(&self, path: &str) -> Result<FileData, AssError> {
+        let path = AssPath::parse(path)?;
+        self.lock()
+            .find_file(&path)
+            .map(|f| f.file.clone())
+            .ok_or_else(|| AssError::not_found(format!("files/path/{}", path)))
+    }
+
+    async fn get_file_rendition(&self, file_id: u64) -> Result<ImageData, AssError> {
+        let mut index = self.lock();
+        let file = index
+            .files
+            .get(&file_id)
+            .ok_or_else(|| AssError::not_found(format!("files/{}", file_id)))?;
+        if let Some(image) = file.rendition.and_then(|id| index.images.get(&id)) {
+            return Ok(image.image.clone());
+        }
+
+        let path = AssPath::parse(&file.file.path)?;
+        let name = path.file_name().unwrap_or_default().to_string();
+        let content = std::fs::read(self.file_path(&path))?;
+        let mut updated = index.clone();
+        let image_id = self.store_image(&mut updated, &content, &name, Acl::default())?;
+        if let Some(file) = updated.files.get_mut(&file_id) {
+            file.rendition = Some(image_id);
+        }
+        self.save(&mut index, updated)?;
+        Ok(index.images[&image_id].image.clone())
+    }
+
+    async fn get_image_information(&self, image_id: u64) -> Result<ImageData, AssError> {
+        self.lock()
+            .images
+            .get(&image_id)
+            .map(|i| i.image.clone())
+            .ok_or_else(|| AssError::not_found(format!("images/{}", image_id)))
+    }
+
+    async fn search(&self, queries: &[(&str, &str)]) -> Result<Vec<FileData>, AssError> {
+        Ok(self
+            .lock()
+            .files
+            .values()
+            .map(|f| &f.file)
+            .filter(|file| {
+                queries
+                    .iter()
+                    .all(|(key, value)| matches_query(file, key, value))
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn delete_file(&self, id: u64) -> Result<(), AssError> {
+        let mut index = self.lock();
+        let mut updated = index.clone();
+        let file = updated
+            .files
+            .remove(&id)
+            .ok_or_else(|| AssError::not_found(format!("files/{}", id)))?;
+        let path = AssPath::parse(&file.file.path)?;
+        self.save(&mut index, updated)?;
+        remove_if_exists(&self.file_path(&path))
+    }
+
+    async fn delete_image(&self, image_id: u64) -> Result<(), AssError> {
+        let mut index = self.lock();
+        let mut updated = index.clone();
+        let image = updated
+            .images
+            .remove(&image_id)
+            .ok_or_else(|| AssError::not_found(format!("images/{}", image_id)))?;
+        self.save(&mut index, updated)?;
+        remove_if_exists(&self.image_path(image_id, &image.extension))
+    }
+
+    fn file_url(&self, path: &str) -> Result<String, AssError> {
+        let path = AssPath::parse(path)?;
+        self.file_url(&self.file_path(&path))
+    }
+
+    fn image_url(&self, image_id: u64) -> Result<String, AssError> {
+        let index = self.lock();
+        let image = index
+            .images
+            .get(&image_id)
+            .ok_or_else(|| AssError::not_found(format!("images/{}", image_id)))?;
+        self.file_url(&self.image_path(image_id, &image.extension))
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<(), AssError> {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AssErrorKind, CollisionPolicy, LocalStorage, SmoothStorage, UploadOptions};
+    use std::path::{Path, PathBuf};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    fn empty_directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        directory
+    }
+
+    fn write_png(directory: &Path, name: &str) -> PathBuf {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 0x40, 0, 0, 0, 0x30]);
+        std::fs::create_dir_all(directory).expect("Could not create directory");
+        let path = directory.join(name);
+        std::fs::write(&path, png).expect("Could not write image");
+        path
+    }
+
+    #[test]
+    fn test_local_file_upload() {
+        let directory = empty_directory("ass-rs-local-upload");
+        let storage = LocalStorage::open(&directory).expect("Could not open storage");
+
+        let options = UploadOptions::new()
+            .metadata("article_id", "12")
+            .expect("Could not set metadata");
+        let file = aw!(storage.upload_file(Path::new("./data/account.json"), "docs", &options))
+            .expect("Could not upload file");
+        assert_eq!(file.id, 1);
+        assert_eq!(file.path, "docs/account.json");
+        assert_eq!(file.content_type, "application/json");
+        let content = std::fs::read("./data/account.json").expect("Could not read file");
+        assert_eq!(file.md5, format!("{:x}", md5::compute(&content)));
+        assert!(file.original_url.starts_with("file://"));
+
+        let found =
+            aw!(storage.search(&[("metadata[article_id]", "12")])).expect("Could not search");
+        assert_eq!(found.len(), 1);
+
+        let storage = LocalStorage::open(&directory).expect("Could not reopen storage");
+        let by_path = aw!(storage.get_file_information_by_path("/docs/account.json"))
+            .expect("Could not get file");
+        assert_eq!(by_path.id, file.id);
+        assert_eq!(by_path.md5, file.md5);
+    }
+
+    #[test]
+    fn test_local_file_collisions() {
+        let directory = empty_directory("ass-rs-local-collisions");
+        let storage = LocalStorage::open(&directory).expect("Could not open storage");
+        let path = Path::new("./data/account.json");
+
+        let file = aw!(storage.upload_file(path, "docs", &UploadOptions::new()))
+            .expect("Could not upload file");
+        let overwritten = aw!(storage.upload_file(path, "docs", &UploadOptions::new()))
+            .expect("Could not overwrite file");
+        assert_eq!(overwritten.id, file.id);
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Fail);
+        match aw!(storage.upload_file(path, "docs", &options)) {
+            Err(e) => assert_eq!(
+                e.kind,
+                AssErrorKind::AlreadyExists("docs/account.json".to_string())
+            ),
+            Ok(_) => panic!("Upload should fail on existing file"),
+        }
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Rename);
+        let renamed =
+            aw!(storage.upload_file(path, "docs", &options)).expect("Could not upload file");
+        assert_eq!(renamed.path, "docs/account-1.json");
+        assert_eq!(renamed.id, 2);
+    }
+
+    #[test]
+    fn test_local_images() {
+        let directory = empty_directory("ass-rs-local-images");
+        let storage =
+            LocalStorage::open(directory.join("storage")).expect("Could not open storage");
+        let path = write_png(&directory, "image.png");
+
+        let image = aw!(storage.upload_image(&path, &UploadOptions::new()))
+            .expect("Could not upload image");
+        assert_eq!((image.width, image.height), (64, 48));
+        assert_eq!(image.name, "image.png");
+
+        let file = aw!(storage.upload_file(&path, "images", &UploadOptions::new()))
+            .expect("Could not upload file");
+        let rendition = aw!(storage.get_file_rendition(file.id)).expect("Could not get rendition");
+        assert_eq!(rendition.width, 64);
+        let cached = aw!(storage.get_file_rendition(file.id)).expect("Could not get rendition");
+        assert_eq!(cached.id, rendition.id);
+
+        let not_an_image =
+            aw!(storage.upload_image(Path::new("./data/account.json"), &UploadOptions::new()));
+        assert!(matches!(
+            not_an_image.map_err(|e| e.kind),
+            Err(AssErrorKind::InvalidImage(_))
+        ));
+    }
+
+    #[test]
+    fn test_local_delete() {
+        let directory = empty_directory("ass-rs-local-delete");
+        let storage = LocalStorage::open(&directory).expect("Could not open storage");
+
+        let file = aw!(storage.upload_file(
+            Path::new("./data/account.json"),
+            "docs",
+            &UploadOptions::new()
+        ))
+        .expect("Could not upload file");
+        aw!(storage.delete_file(file.id)).expect("Could not delete file");
+        assert!(!directory.join("files/docs/account.json").exists());
+
+        match aw!(storage.get_file_information_by_id(file.id)) {
+            Err(e) => assert_eq!(e.kind, AssErrorKind::NotFound("files/1".to_string())),
+            Ok(_) => panic!("File should be deleted"),
+        }
+    }
+
+    #[test]
+    fn test_local_failed_save_keeps_index() {
+        let directory = empty_directory("ass-rs-local-failed-save");
+        let storage = LocalStorage::open(&directory).expect("Could not open storage");
+        let path = Path::new("./data/account.json");
+
+        // A directory in the way of the temporary index file makes saving fail
+        let blocker = directory.join("index.json.tmp");
+        std::fs::create_dir(&blocker).expect("Could not create directory");
+        assert!(aw!(storage.upload_file(path, "docs", &UploadOptions::new())).is_err());
+        assert!(aw!(storage.get_file_information_by_path("docs/account.json")).is_err());
+
+        std::fs::remove_dir(&blocker).expect("Could not remove directory");
+        let file = aw!(storage.upload_file(path, "docs", &UploadOptions::new()))
+            .expect("Could not upload file");
+        assert_eq!(file.id, 1);
+
+        std::fs::create_dir(&blocker).expect("Could not create directory");
+        assert!(aw!(storage.delete_file(file.id)).is_err());
+        assert!(aw!(storage.get_file_information_by_id(file.id)).is_ok());
+        assert!(directory.join("files/docs/account.json").exists());
+    }
+}
@@ -0,0 +1,200 @@
+//!
+//! Storage abstraction over smooth storage
+//!
+
+use crate::{
+    file_handling, image_handling, AssClient, AssError, FileData, ImageData, UploadOptions,
+};
+use async_trait::async_trait;
+use std::path::Path;
+
+#[cfg(feature = "local")]
+mod local;
+
+#[cfg(feature = "local")]
+pub use self::local::LocalStorage;
+
+///
+/// The operations offered by smooth storage, implemented by `AssClient`.
+///
+/// Application code can depend on `SmoothStorage` instead of `AssClient`, and substitute other
+/// implementations in tests. The trait is object safe, so it can be used as
+/// `Arc<dyn SmoothStorage>`.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{AssClient, SmoothStorage};
+/// use std::sync::Arc;
+///
+/// let storage: Arc<dyn SmoothStorage> =
+///     Arc::new(AssClient::create("https://storage.url.com", "username", "password")?);
+///
+/// let image_url = storage.image_url(5)?;
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+#[async_trait]
+pub trait SmoothStorage: Send + Sync {
+    /// Upload a file to the destination directory
+    async fn upload_file(
+        &self,
+        path: &Path,
+        destination: &str,
+        options: &UploadOptions,
+    ) -> Result<FileData, AssError>;
+
+    /// Upload an image
+    async fn upload_image(
+        &self,
+        path: &Path,
+        options: &UploadOptions,
+    ) -> Result<ImageData, AssError>;
+
+    /// Fetch file information, queried by id
+    async fn get_file_information_by_id(&self, id: u64) -> Result<FileData, AssError>;
+
+    /// Fetch file information, queried by path
+    async fn get_file_information_by_path(&self, path: &str) -> Result<FileData, AssError>;
+
+    /// Fetch the default image rendition of a file
+    async fn get_file_rendition(&self, file_id: u64) -> Result<ImageData, AssError>;
+
+    /// Fetch image information
+    async fn get_image_information(&self, image_id: u64) -> Result<ImageData, AssError>;
+
+    /// Search for files
+    async fn search(&self, queries: &[(&str, &str)]) -> Result<Vec<FileData>, AssError>;
+
+    /// Delete a file
+    async fn delete_file(&self, id: u64) -> Result<(), AssError>;
+
+    /// Delete an image
+    async fn delete_image(&self, image_id: u64) -> Result<(), AssError>;
+
+    /// Get a url for the file on the given path
+    fn file_url(&self, path: &str) -> Result<String, AssError>;
+
+    /// Get a url for the image with the given id
+    fn image_url(&self, image_id: u64) -> Result<String, AssError>;
+}
+
+#[async_trait]
+impl SmoothStorage for AssClient {
+    async fn upload_file(
+        &self,
+        path: &Path,
+        destination: &str,
+        options: &UploadOptions,
+    ) -> Result<FileData, AssError> {
+        file_handling::upload_file_with_options(self, path, destination, options).await
+    }
+
+    async fn upload_image(
+        &self,
+        path: &Path,
+        options: &UploadOptions,
+    ) -> Result<ImageData, AssError> {
+        image_handling::upload_image_with_options(self, path, options).await
+    }
+
+    async fn get_file_information_by_id(&self, id: u64) -> Result<FileData, AssError> {
+        file_handling::get_file_information_by_id(self, id).await
+    }
+
+    async fn get_file_information_by_path(&self, path: &str) -> Result<FileData, AssError> {
+        file_handling::get_file_information_by_path(self, path).await
+    }
+
+    async fn get_file_rendition(&self, file_id: u64) -> Result<ImageData, AssError> {
+        file_handling::get_file_rendition(self, file_id).await
+    }
+
+    async fn get_image_information(&self, image_id: u64) -> Result<ImageData, AssError> {
+        image_handling::get_image_information(self, image_id).await
+    }
+
+    async fn search(&self, queries: &[(&str, &str)]) -> Result<Vec<FileData>, AssError> {
+        file_handling::search(self, queries).await
+    }
+
+    async fn delete_file(&self, id: u64) -> Result<(), AssError> {
+        file_handling::delete_file(self, id).await
+    }
+
+    async fn delete_image(&self, image_id: u64) -> Result<(), AssError> {
+        image_handling::delete_image(self, image_id).await
+    }
+
+    fn file_url(&self, path: &str) -> Result<String, AssError> {
+        file_handling::get_file_url(self, path)
+    }
+
+    fn image_url(&self, image_id: u64) -> Result<String, AssError> {
+        image_handling::get_image_url(self, image_id)
+    }
+}
+
+/// Check a file against a search query, the way smooth storage does for the `path` prefix,
+/// `md5`, `content_type` and `metadata[key]` queries. Unknown queries match everything
+#[cfg(any(feature = "local", feature = "testing"))]
+pub(crate) fn matches_query(file: &FileData, key: &str, value: &str) -> bool {
+    match key {
+        "path" => file.path.starts_with(value),
+        "md5" => file.md5 == value,
+        "content_type" => file.content_type == value,
+        key if key.starts_with("metadata[") && key.ends_with(']') => {
+            let key = &key["metadata[".len()..key.len() - 1];
+            file.metadata.get(key).map(|v| v.as_str()) == Some(value)
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AssClient, SmoothStorage};
+    use std::sync::Arc;
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    #[test]
+    fn test_storage_trait_object() {
+        let _m = mockito::mock("GET", "/files/4")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{\"id\": 4, \"user_id\": 3, \"path\": \"path\", \"md5\": \"checksum\", \"content_type\": \"text\", \"original_url\": \"url.com\", \"created\": \"2013-08-21T09:30:50.068Z\", \"updated\": \"2013-08-21T09:30:50.068Z\"}")
+            .create();
+
+        let url = &mockito::server_url();
+
+        let storage: Arc<dyn SmoothStorage> =
+            Arc::new(AssClient::create(url, "account", "apikey").expect("Could not get AssClient"));
+
+        let result = aw!(storage.get_file_information_by_id(4)).expect("Could not get result");
+        assert_eq!(result.id, 4);
+    }
+
+    #[test]
+    fn test_storage_delete_file() {
+        let m = mockito::mock("DELETE", "/files/5")
+            .match_header("Authorization", "bearer apikey")
+            .with_status(204)
+            .create();
+
+        let url = &mockito::server_url();
+
+        let storage: Arc<dyn SmoothStorage> =
+            Arc::new(AssClient::create(url, "account", "apikey").expect("Could not get AssClient"));
+
+        aw!(storage.delete_file(5)).expect("Could not delete file");
+        m.assert();
+    }
+}
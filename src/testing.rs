@@ -0,0 +1,764 @@
+//!
+//! In-memory fake of smooth storage, for testing code that uses this crate.
+//!
+//! `FakeServer` runs a local HTTP server implementing the files and images endpoints used by
+//! `file_handling` and `image_handling`. It keeps uploads in memory, assigns ids, computes md5
+//! checksums and image dimensions, checks the bearer token on every api request and verifies
+//! signed urls when serving content.
+//!
+//! ```rust
+//! # use ass_rs::AssError;
+//! # fn main() -> Result<(), AssError> {
+//! use ass_rs::testing::FakeServer;
+//! use ass_rs::file_handling;
+//!
+//! let server = FakeServer::start("account", "apikey")?;
+//! let ass_client = server.client()?;
+//!
+//! let file = tokio_test::block_on(file_handling::upload_file(
+//!     &ass_client,
+//!     "./data/account.json",
+//!     "documents",
+//! ))?;
+//! assert_eq!(file.path, "documents/account.json");
+//! assert_eq!(server.files().len(), 1);
+//!
+//! # Ok(())
+//! # }
+//! ```
+//!
+
+use crate::image_size::image_info;
+use crate::storage::matches_query;
+use crate::{Acl, AssClient, AssError, FileData, ImageData};
+use chrono::Utc;
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::http::request::Parts;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tokio::sync::oneshot;
+
+const USER_ID: u64 = 1;
+
+struct StoredFile {
+    data: FileData,
+    content: Vec<u8>,
+    acl: Acl,
+    rendition: Option<u64>,
+}
+
+struct StoredImage {
+    data: ImageData,
+    content: Vec<u8>,
+    acl: Acl,
+}
+
+struct State {
+    verifier: AssClient,
+    apikey: String,
+    next_file_id: u64,
+    next_image_id: u64,
+    files: BTreeMap<u64, StoredFile>,
+    images: BTreeMap<u64, StoredImage>,
+}
+
+struct FormPart {
+    name: String,
+    content_type: Option<String>,
+    data: Vec<u8>,
+}
+
+///
+/// A fake smooth storage server running on a local port.
+///
+/// The server is stopped when the `FakeServer` is dropped.
+///
+pub struct FakeServer {
+    url: String,
+    name: String,
+    apikey: String,
+    state: Arc<Mutex<State>>,
+    shutdown: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FakeServer {
+    /// Start a server for the given account name and api key
+    pub fn start<U: Into<String>, V: Into<String>>(name: U, apikey: V) -> Result<Self, AssError> {
+        let name = name.into();
+        let apikey = apikey.into();
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}/", listener.local_addr()?);
+
+        let state = Arc::new(Mutex::new(State {
+            verifier: AssClient::create(url.as_str(), name.as_str(), apikey.as_str())?,
+            apikey: apikey.clone(),
+            next_file_id: 1,
+            next_image_id: 1,
+            files: BTreeMap::new(),
+            images: BTreeMap::new(),
+        }));
+
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()?;
+        let (shutdown, shutdown_signal) = oneshot::channel::<()>();
+        let service_state = state.clone();
+        let thread = std::thread::spawn(move || {
+            runtime.block_on(async move {
+                let make_service = make_service_fn(move |_| {
+                    let state = service_state.clone();
+                    async move {
+                        Ok::<_, Infallible>(service_fn(move |req| handle(state.clone(), req)))
+                    }
+                });
+                let server = Server::from_tcp(listener)
+                    .expect("Could not start fake server")
+                    .serve(make_service)
+                    .with_graceful_shutdown(async {
+                        shutdown_signal.await.ok();
+                    });
+                server.await.expect("Fake server failed");
+            })
+        });
+
+        Ok(FakeServer {
+            url,
+            name,
+            apikey,
+            state,
+            shutdown: Some(shutdown),
+            thread: Some(thread),
+        })
+    }
+
+    /// The server's base url
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Create a client with the server's url and credentials
+    pub fn client(&self) -> Result<AssClient, AssError> {
+        AssClient::create(self.url.as_str(), self.name.as_str(), self.apikey.as_str())
+    }
+
+    /// All files currently stored
+    pub fn files(&self) -> Vec<FileData> {
+        let state = self.state.lock().expect("Fake server state poisoned");
+        state.files.values().map(|f| f.data.clone()).collect()
+    }
+
+    /// All images currently stored
+    pub fn images(&self) -> Vec<ImageData> {
+        let state = self.state.lock().expect("Fake server state poisoned");
+        state.images.values().map(|i| i.data.clone()).collect()
+    }
+
+    /// The content of a stored file
+    pub fn file_content(&self, id: u64) -> Option<Vec<u8>> {
+        let state = self.state.lock().expect("Fake server state poisoned");
+        state.files.get(&id).map(|f| f.content.clone())
+    }
+
+    /// The content of a stored image
+    pub fn image_content(&self, id: u64) -> Option<Vec<u8>> {
+        let state = self.state.lock().expect("Fake server state poisoned");
+        state.images.get(&id).map(|i| i.content.clone())
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+async fn handle(
+    state: Arc<Mutex<State>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let (parts, body) = req.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => return Ok(error(StatusCode::BAD_REQUEST, &err.to_string())),
+    };
+    let mut state = state.lock().expect("Fake server state poisoned");
+    Ok(state.route(&parts, &body))
+}
+
+impl State {
+    fn route(&mut self, parts: &Parts, body: &[u8]) -> Response<Body> {
+        let segments: Vec<String> = parts
+            .uri
+            .path()
+            .trim_start_matches('/')
+            .split('/')
+            .map(|s| percent_decode_str(s).decode_utf8_lossy().into_owned())
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(|s| s.as_str()).collect();
+
+        if segments.first() == Some(&"users") {
+            return self.serve_content(parts, &segments);
+        }
+
+        let authorized = parts
+            .headers
+            .get(AUTHORIZATION)
+            .map(|value| value.as_bytes() == format!("bearer {}", self.apikey).as_bytes())
+            .unwrap_or(false);
+        if !authorized {
+            return error(StatusCode::UNAUTHORIZED, "Invalid api key");
+        }
+
+        let method = &parts.method;
+        match segments.as_slice() {
+            ["files"] if method == Method::GET => self.search(parts.uri.query().unwrap_or("")),
+            ["files", "path", path @ ..] if method == Method::GET => {
+                let path = path.join("/");
+                match self.files.values().find(|f| f.data.path == path) {
+                    Some(file) => json(StatusCode::OK, &file.data),
+                    None => not_found(),
+                }
+            }
+            ["files", id] if method == Method::GET => match self.file(id) {
+                Some(file) => json(StatusCode::OK, &file.data),
+                None => not_found(),
+            },
+            ["files", id] if method == Method::DELETE => {
+                match id.parse().ok().and_then(|id: u64| self.files.remove(&id)) {
+                    Some(_) => empty(StatusCode::NO_CONTENT),
+                    None => not_found(),
+                }
+            }
+            ["files", id, "image"] if method == Method::GET => self.rendition(id),
+            ["files", id, "metadata"] if method == Method::PUT => {
+                let metadata: HashMap<String, String> = match serde_json::from_slice(body) {
+                    Ok(metadata) => metadata,
+                    Err(err) => return error(StatusCode::BAD_REQUEST, &err.to_string()),
+                };
+                match self.file_mut(id) {
+                    Some(file) => {
+                        file.data.metadata = metadata;
+                        file.data.updated = Utc::now();
+                        json(StatusCode::OK, &file.data)
+                    }
+                    None => not_found(),
+                }
+            }
+            ["files", id, "acl"] if method == Method::PUT => match parse_acl(body) {
+                Ok(acl) => match self.file_mut(id) {
+                    Some(file) => {
+                        file.acl = acl;
+                        file.data.updated = Utc::now();
+                        json(StatusCode::OK, &file.data)
+                    }
+                    None => not_found(),
+                },
+                Err(message) => error(StatusCode::BAD_REQUEST, &message),
+            },
+            ["files", path @ ..] if method == Method::POST && !path.is_empty() => {
+                self.upload_file(parts, &path.join("/"), body)
+            }
+            ["images"] if method == Method::POST => self.upload_image(parts, body),
+            ["images", id] if method == Method::GET => {
+                match id.parse().ok().and_then(|id: u64| self.images.get(&id)) {
+                    Some(image) => json(StatusCode::OK, &image.data),
+                    None => not_found(),
+                }
+            }
+            ["images", id] if method == Method::DELETE => {
+                match id.parse().ok().and_then(|id: u64| self.images.remove(&id)) {
+                    Some(_) => empty(StatusCode::NO_CONTENT),
+                    None => not_found(),
+                }
+            }
+            ["images", id, "acl"] if method == Method::PUT => match parse_acl(body) {
+                Ok(acl) => match id.parse().ok().and_then(|id: u64| self.images.get_mut(&id)) {
+                    Some(image) => {
+                        image.acl = acl;
+                        image.data.updated = Utc::now();
+                        json(StatusCode::OK, &image.data)
+                    }
+                    None => not_found(),
+                },
+                Err(message) => error(StatusCode::BAD_REQUEST, &message),
+            },
+            _ => not_found(),
+        }
+    }
+
+    fn file(&self, id: &str) -> Option<&StoredFile> {
+        id.parse().ok().and_then(|id: u64| self.files.get(&id))
+    }
+
+    fn file_mut(&mut self, id: &str) -> Option<&mut StoredFile> {
+        id.parse()
+            .ok()
+            .and_then(move |id: u64| self.files.get_mut(&id))
+    }
+
+    fn search(&self, query: &str) -> Response<Body> {
+        let queries: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let files: Vec<&FileData> = self
+            .files
+            .values()
+            .map(|f| &f.data)
+            .filter(|file| {
+                queries
+                    .iter()
+                    .all(|(key, value)| matches_query(file, key, value))
+            })
+            .collect();
+        json(StatusCode::OK, &files)
+    }
+
+    fn rendition(&mut self, id: &str) -> Response<Body> {
+        let (file_id, content, existing) = match self.file(id) {
+            Some(file) => (file.data.id, file.content.clone(), file.rendition),
+            None => return not_found(),
+        };
+        let image_id = match existing {
+            Some(image_id) if self.images.contains_key(&image_id) => image_id,
+            _ => match self.store_image(content, None, Acl::Public) {
+                Some(image_id) => image_id,
+                None => return error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Not an image"),
+            },
+        };
+        if let Some(file) = self.files.get_mut(&file_id) {
+            file.rendition = Some(image_id);
+        }
+        json(StatusCode::OK, &serde_json::json!({ "image_id": image_id }))
+    }
+
+    fn upload_file(&mut self, parts: &Parts, path: &str, body: &[u8]) -> Response<Body> {
+        let form = match parse_form(parts, body) {
+            Some(form) => form,
+            None => return error(StatusCode::BAD_REQUEST, "Invalid multipart body"),
+        };
+        let file = match form.iter().find(|p| p.name == "file") {
+            Some(file) => file,
+            None => return error(StatusCode::BAD_REQUEST, "Missing file"),
+        };
+        let metadata: HashMap<String, String> = match form.iter().find(|p| p.name == "metadata") {
+            Some(part) => match serde_json::from_slice(&part.data) {
+                Ok(metadata) => metadata,
+                Err(err) => return error(StatusCode::BAD_REQUEST, &err.to_string()),
+            },
+            None => HashMap::new(),
+        };
+        let acl = match header_acl(parts) {
+            Ok(acl) => acl,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message),
+        };
+        let overwrite = parts
+            .headers
+            .get("x-ass-overwrite")
+            .map(|v| v.as_bytes() != b"false")
+            .unwrap_or(true);
+
+        let existing = self
+            .files
+            .values()
+            .find(|f| f.data.path == path)
+            .map(|f| (f.data.id, f.data.created));
+        if existing.is_some() && !overwrite {
+            return error(StatusCode::CONFLICT, "File already exists");
+        }
+        let (id, created) = existing.unwrap_or_else(|| {
+            self.next_file_id += 1;
+            (self.next_file_id - 1, Utc::now())
+        });
+
+        let content_type = file.content_type.clone().unwrap_or_else(|| {
+            mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string()
+        });
+        let original_url = self
+            .verifier
+            .url()
+            .join(&format!("users/{}/files/{}", self.verifier.name, path))
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+        let data = FileData {
+            id,
+            user_id: USER_ID,
+            path: path.to_string(),
+            md5: format!("{:x}", md5::compute(&file.data)),
+            content_type,
+            original_url,
+            created,
+            updated: Utc::now(),
+            metadata,
+        };
+        self.files.insert(
+            id,
+            StoredFile {
+                data: data.clone(),
+                content: file.data.clone(),
+                acl,
+                rendition: None,
+            },
+        );
+        json(StatusCode::CREATED, &data)
+    }
+
+    fn upload_image(&mut self, parts: &Parts, body: &[u8]) -> Response<Body> {
+        let form = match parse_form(parts, body) {
+            Some(form) => form,
+            None => return error(StatusCode::BAD_REQUEST, "Invalid multipart body"),
+        };
+        let file = match form.into_iter().find(|p| p.name == "file") {
+            Some(file) => file,
+            None => return error(StatusCode::BAD_REQUEST, "Missing file"),
+        };
+        let acl = match header_acl(parts) {
+            Ok(acl) => acl,
+            Err(message) => return error(StatusCode::BAD_REQUEST, &message),
+        };
+        match self.store_image(file.data, None, acl) {
+            Some(id) => json(StatusCode::CREATED, &self.images[&id].data),
+            None => error(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Not an image"),
+        }
+    }
+
+    fn store_image(&mut self, content: Vec<u8>, name: Option<String>, acl: Acl) -> Option<u64> {
+        let (format, width, height) = image_info(&content)?;
+        let id = self.next_image_id;
+        self.next_image_id += 1;
+        let original_url = self
+            .verifier
+            .url()
+            .join(&format!(
+                "users/{}/images/{}.{}",
+                self.verifier.name,
+                id,
+                format.extension()
+            ))
+            .map(|url| url.to_string())
+            .unwrap_or_default();
+        let now = Utc::now();
+        let data = ImageData {
+            id,
+            user_id: USER_ID,
+            md5: format!("{:x}", md5::compute(&content)),
+            original_url,
+            width,
+            height,
+            name: name.unwrap_or_else(|| format!("{}.{}", id, format.extension())),
+            title: None,
+            description: None,
+            author: None,
+            source_url: None,
+            created: now,
+            updated: now,
+        };
+        self.images.insert(id, StoredImage { data, content, acl });
+        Some(id)
+    }
+
+    /// Serve the content of files and images on the public `users/{name}/` urls, verifying
+    /// access tokens and requiring one for private resources
+    fn serve_content(&self, parts: &Parts, segments: &[&str]) -> Response<Body> {
+        let (content, acl) = match segments {
+            ["users", name, "files", path @ ..] if *name == self.verifier.name => {
+                let path = path.join("/");
+                match self.files.values().find(|f| f.data.path == path) {
+                    Some(file) => (&file.content, file.acl),
+                    None => return not_found(),
+                }
+            }
+            ["users", name, "images", image] if *name == self.verifier.name => {
+                let id = image.split('.').next().unwrap_or("");
+                match id.parse().ok().and_then(|id: u64| self.images.get(&id)) {
+                    Some(image) => (&image.content, image.acl),
+                    None => return not_found(),
+                }
+            }
+            _ => return not_found(),
+        };
+
+        let signed = parts
+            .uri
+            .query()
+            .map(|q| q.split('&').any(|pair| pair.starts_with("accessToken=")))
+            .unwrap_or(false);
+        if signed {
+            let url = format!(
+                "{}{}",
+                self.verifier.url_string().trim_end_matches('/'),
+                parts.uri
+            );
+            if let Err(err) = self.verifier.verify_signed_url(&url) {
+                return error(StatusCode::FORBIDDEN, &err.to_string());
+            }
+        } else if acl == Acl::Private {
+            return error(StatusCode::FORBIDDEN, "Missing access token");
+        }
+
+        Response::builder()
+            .status(StatusCode::OK)
+            .body(Body::from(content.clone()))
+            .expect("Could not build response")
+    }
+}
+
+fn header_acl(parts: &Parts) -> Result<Acl, String> {
+    match parts.headers.get("x-ass-acl").map(|v| v.to_str()) {
+        Some(Ok(acl)) => acl.parse().map_err(|err: AssError| err.to_string()),
+        Some(Err(err)) => Err(err.to_string()),
+        None => Ok(Acl::default()),
+    }
+}
+
+fn parse_acl(body: &[u8]) -> Result<Acl, String> {
+    #[derive(serde::Deserialize)]
+    struct AclBody {
+        acl: Acl,
+    }
+    serde_json::from_slice::<AclBody>(body)
+        .map(|body| body.acl)
+        .map_err(|err| err.to_string())
+}
+
+/// Parse a `multipart/form-data` body into its parts
+fn parse_form(parts: &Parts, body: &[u8]) -> Option<Vec<FormPart>> {
+    let content_type = parts.headers.get(CONTENT_TYPE)?.to_str().ok()?;
+    let boundary = content_type
+        .split(';')
+        .map(|s| s.trim())
+        .find(|s| s.starts_with("boundary="))?["boundary=".len()..]
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    let mut form = vec![];
+    for section in split(body, &delimiter).into_iter().skip(1) {
+        if section.starts_with(b"--") {
+            break;
+        }
+        let section = section.strip_prefix(b"\r\n")?;
+        let section = section.strip_suffix(b"\r\n").unwrap_or(section);
+        let header_end = find(section, b"\r\n\r\n")?;
+        let headers = String::from_utf8_lossy(&section[..header_end]);
+        let data = section[header_end + 4..].to_vec();
+
+        let mut name = None;
+        let mut content_type = None;
+        for header in headers.split("\r\n") {
+            let (key, value) = match header.find(':') {
+                Some(i) => (header[..i].trim().to_lowercase(), header[i + 1..].trim()),
+                None => continue,
+            };
+            if key == "content-disposition" {
+                name = value
+                    .split(';')
+                    .map(|s| s.trim())
+                    .find(|s| s.starts_with("name="))
+                    .map(|s| s["name=".len()..].trim_matches('"').to_string());
+            } else if key == "content-type" {
+                content_type = Some(value.to_string());
+            }
+        }
+        form.push(FormPart {
+            name: name?,
+            content_type,
+            data,
+        });
+    }
+    Some(form)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn split<'a>(mut haystack: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut sections = vec![];
+    while let Some(i) = find(haystack, delimiter) {
+        sections.push(&haystack[..i]);
+        haystack = &haystack[i + delimiter.len()..];
+    }
+    sections.push(haystack);
+    sections
+}
+
+fn json<T: Serialize + ?Sized>(status: StatusCode, data: &T) -> Response<Body> {
+    match serde_json::to_vec(data) {
+        Ok(body) => Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .expect("Could not build response"),
+        Err(err) => error(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string()),
+    }
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("Could not build response")
+}
+
+fn not_found() -> Response<Body> {
+    error(StatusCode::NOT_FOUND, "Not found")
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    let body = serde_json::json!({ "error": message }).to_string();
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("Could not build response")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::FakeServer;
+    use crate::{file_handling, image_handling, Acl, AssClient, CollisionPolicy, UploadOptions};
+
+    macro_rules! aw {
+        ($e:expr) => {
+            tokio_test::block_on($e)
+        };
+    }
+
+    fn write_png(name: &str) -> std::path::PathBuf {
+        let mut png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 0x20, 0, 0, 0, 0x10]);
+        let dir = std::env::temp_dir().join("ass-rs-fake-server-test");
+        std::fs::create_dir_all(&dir).expect("Could not create directory");
+        let path = dir.join(name);
+        std::fs::write(&path, png).expect("Could not write image");
+        path
+    }
+
+    #[test]
+    fn fake_server_files() {
+        let server = FakeServer::start("account", "apikey").expect("Could not start server");
+        let ass_client = server.client().expect("Could not get AssClient");
+
+        let options = UploadOptions::new()
+            .metadata("article_id", "12")
+            .expect("Could not set metadata");
+        let file = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "docs",
+            &options
+        ))
+        .expect("Could not upload file");
+        assert_eq!(file.id, 1);
+        assert_eq!(file.path, "docs/account.json");
+        assert_eq!(file.content_type, "application/json");
+        let content = std::fs::read("./data/account.json").expect("Could not read file");
+        assert_eq!(file.md5, format!("{:x}", md5::compute(&content)));
+
+        let by_path = aw!(file_handling::get_file_information_by_path(
+            &ass_client,
+            "docs/account.json"
+        ))
+        .expect("Could not get file");
+        assert_eq!(by_path.id, file.id);
+
+        let found = aw!(file_handling::search_by_metadata(
+            &ass_client,
+            &[("article_id", "12")]
+        ))
+        .expect("Could not search");
+        assert_eq!(found.len(), 1);
+
+        let options = UploadOptions::new().collision_policy(CollisionPolicy::Rename);
+        let renamed = aw!(file_handling::upload_file_with_options(
+            &ass_client,
+            "./data/account.json",
+            "docs",
+            &options
+        ))
+        .expect("Could not upload file");
+        assert_eq!(renamed.path, "docs/account-1.json");
+
+        aw!(file_handling::delete_file(&ass_client, file.id)).expect("Could not delete file");
+        assert!(aw!(file_handling::get_file_information_by_id(
+            &ass_client,
+            file.id
+        ))
+        .is_err());
+        assert_eq!(server.files().len(), 1);
+    }
+
+    #[test]
+    fn fake_server_images() {
+        let server = FakeServer::start("account", "apikey").expect("Could not start server");
+        let ass_client = server.client().expect("Could not get AssClient");
+
+        let path = write_png("image.png");
+        let image = aw!(image_handling::upload_image_with_acl(
+            &ass_client,
+            &path,
+            Acl::Private
+        ))
+        .expect("Could not upload image");
+        assert_eq!((image.width, image.height), (32, 16));
+
+        let file = aw!(file_handling::upload_file(&ass_client, &path, "images"))
+            .expect("Could not upload file");
+        let rendition = aw!(file_handling::get_file_rendition(&ass_client, file.id))
+            .expect("Could not get rendition");
+        assert_eq!(rendition.width, 32);
+        assert_eq!(server.images().len(), 2);
+    }
+
+    #[test]
+    fn fake_server_checks_credentials() {
+        let server = FakeServer::start("account", "apikey").expect("Could not start server");
+        let ass_client = server.client().expect("Could not get AssClient");
+
+        let path = write_png("private.png");
+        let image = aw!(image_handling::upload_image_with_acl(
+            &ass_client,
+            &path,
+            Acl::Private
+        ))
+        .expect("Could not upload image");
+
+        let url = image_handling::get_image_url(&ass_client, image.id).expect("Could not sign");
+        let response = aw!(reqwest::get(&url)).expect("Could not fetch image");
+        assert_eq!(response.status(), 200);
+
+        let response = aw!(reqwest::get(&image.original_url)).expect("Could not fetch image");
+        assert_eq!(response.status(), 403);
+
+        let response = aw!(reqwest::get(&url.replace("accessToken=", "accessToken=0")))
+            .expect("Could not fetch image");
+        assert_eq!(response.status(), 403);
+
+        let wrong_client =
+            AssClient::create(server.url(), "account", "wrong").expect("Could not get AssClient");
+        assert!(aw!(image_handling::get_image_information(
+            &wrong_client,
+            image.id
+        ))
+        .is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use crate::client::ACL_HEADER;
+use crate::{Acl, AssError, FilenameSanitizer};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::multipart::{Form, Part};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+
+pub(crate) const OVERWRITE_HEADER: &str = "x-ass-overwrite";
+pub(crate) const ORIGINAL_FILENAME_KEY: &str = "original_filename";
+
+/// Headers that are set by the client or through typed options, and can not be passed as extra
+/// headers
+const RESERVED_HEADERS: &[&str] = &[
+    "authorization",
+    "accept",
+    "cache-control",
+    "content-disposition",
+    "content-length",
+    "content-type",
+    "host",
+    "transfer-encoding",
+    ACL_HEADER,
+    OVERWRITE_HEADER,
+];
+
+/// Characters allowed unencoded in an RFC 5987 `filename*` value
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+///
+/// Cache-Control header value for uploaded resources.
+///
+/// ```rust
+/// use ass_rs::CacheControl;
+/// use std::time::Duration;
+///
+/// let cache_control = CacheControl::max_age(Duration::from_secs(3600)).public().immutable();
+/// assert_eq!(cache_control.to_string(), "public, max-age=3600, immutable");
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    public: bool,
+    private: bool,
+    no_cache: bool,
+    no_store: bool,
+    max_age: Option<Duration>,
+    immutable: bool,
+}
+
+impl CacheControl {
+    /// Allow caching for the given duration
+    pub fn max_age(max_age: Duration) -> Self {
+        CacheControl {
+            max_age: Some(max_age),
+            ..Default::default()
+        }
+    }
+
+    /// Require caches to revalidate before using a stored response
+    pub fn no_cache() -> Self {
+        CacheControl {
+            no_cache: true,
+            ..Default::default()
+        }
+    }
+
+    /// Disallow storing the response in any cache
+    pub fn no_store() -> Self {
+        CacheControl {
+            no_store: true,
+            ..Default::default()
+        }
+    }
+
+    /// Allow shared caches to store the response
+    pub fn public(mut self) -> Self {
+        self.public = true;
+        self.private = false;
+        self
+    }
+
+    /// Only allow private caches to store the response
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self.public = false;
+        self
+    }
+
+    /// Mark the response as never changing while fresh
+    pub fn immutable(mut self) -> Self {
+        self.immutable = true;
+        self
+    }
+}
+
+impl Display for CacheControl {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        let mut directives = vec![];
+        if self.public {
+            directives.push("public".to_string());
+        }
+        if self.private {
+            directives.push("private".to_string());
+        }
+        if self.no_cache {
+            directives.push("no-cache".to_string());
+        }
+        if self.no_store {
+            directives.push("no-store".to_string());
+        }
+        if let Some(max_age) = self.max_age {
+            directives.push(format!("max-age={}", max_age.as_secs()));
+        }
+        if self.immutable {
+            directives.push("immutable".to_string());
+        }
+        write!(fmt, "{}", directives.join(", "))
+    }
+}
+
+///
+/// Content-Disposition header value for uploaded resources.
+///
+/// ```rust
+/// use ass_rs::ContentDisposition;
+///
+/// let disposition = ContentDisposition::attachment("rapport æøå.pdf");
+/// assert_eq!(
+///     disposition.to_string(),
+///     "attachment; filename=\"rapport ___.pdf\"; filename*=UTF-8''rapport%20%C3%A6%C3%B8%C3%A5.pdf"
+/// );
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentDisposition {
+    /// Display the resource in the browser
+    Inline,
+    /// Download the resource, optionally with the given file name
+    Attachment { filename: Option<String> },
+}
+
+impl ContentDisposition {
+    /// Download the resource with the given file name
+    pub fn attachment<T: Into<String>>(filename: T) -> Self {
+        ContentDisposition::Attachment {
+            filename: Some(filename.into()),
+        }
+    }
+}
+
+impl Display for ContentDisposition {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ContentDisposition::Inline => write!(fmt, "inline"),
+            ContentDisposition::Attachment { filename: None } => write!(fmt, "attachment"),
+            ContentDisposition::Attachment {
+                filename: Some(filename),
+            } => {
+                let fallback: String = filename
+                    .chars()
+                    .map(|c| match c {
+                        '"' | '\\' => '_',
+                        c if c.is_ascii() && !c.is_ascii_control() => c,
+                        _ => '_',
+                    })
+                    .collect();
+                write!(fmt, "attachment; filename=\"{}\"", fallback)?;
+                if fallback != *filename {
+                    write!(
+                        fmt,
+                        "; filename*=UTF-8''{}",
+                        utf8_percent_encode(filename, ATTR_CHAR)
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What to do when uploading a file to a path that already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Fail the upload with an `AlreadyExists` error
+    Fail,
+    /// Replace the existing file
+    Overwrite,
+    /// Skip the upload and return the existing file
+    Skip,
+    /// Upload under a new name, adding a numeric suffix such as `report-1.pdf`
+    Rename,
+}
+
+///
+/// Options for uploading files and images.
+///
+/// ```rust
+/// # use ass_rs::AssError;
+/// # fn main() -> Result<(), AssError> {
+/// use ass_rs::{Acl, CacheControl, ContentDisposition, UploadOptions};
+/// use std::time::Duration;
+///
+/// let options = UploadOptions::new()
+///     .acl(Acl::Private)
+///     .cache_control(CacheControl::max_age(Duration::from_secs(600)))
+///     .content_disposition(ContentDisposition::attachment("contract.pdf"))
+///     .content_type("application/pdf")?
+///     .header("x-request-source", "cms")?;
+///
+/// # Ok(())
+/// # }
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct UploadOptions {
+    cache_control: Option<CacheControl>,
+    content_disposition: Option<ContentDisposition>,
+    acl: Option<Acl>,
+    content_type: Option<String>,
+    collision_policy: Option<CollisionPolicy>,
+    metadata: BTreeMap<String, String>,
+    sanitizer: Option<FilenameSanitizer>,
+    headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+impl UploadOptions {
+    /// Create options using the client's defaults
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the Cache-Control header of the uploaded resource
+    pub fn cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+
+    /// Set the Content-Disposition header of the uploaded resource
+    pub fn content_disposition(mut self, content_disposition: ContentDisposition) -> Self {
+        self.content_disposition = Some(content_disposition);
+        self
+    }
+
+    /// Set the acl of the uploaded resource, overriding the client's default acl
+    pub fn acl(mut self, acl: Acl) -> Self {
+        self.acl = Some(acl);
+        self
+    }
+
+    /// Set the content type of the uploaded resource, must be a valid mime type
+    pub fn content_type(mut self, content_type: &str) -> Result<Self, AssError> {
+        content_type
+            .parse::<mime::Mime>()
+            .map_err(|err| AssError::invalid_content_type(content_type.to_string(), err))?;
+        self.content_type = Some(content_type.to_string());
+        Ok(self)
+    }
+
+    /// Set whether an existing file at the destination should be overwritten, shorthand for
+    /// `CollisionPolicy::Overwrite` and `CollisionPolicy::Fail`
+    pub fn overwrite(self, overwrite: bool) -> Self {
+        if overwrite {
+            self.collision_policy(CollisionPolicy::Overwrite)
+        } else {
+            self.collision_policy(CollisionPolicy::Fail)
+        }
+    }
+
+    /// Set what to do when a file already exists at the destination. Without a policy the
+    /// storage's default behaviour applies. Only used for file uploads
+    pub fn collision_policy(mut self, collision_policy: CollisionPolicy) -> Self {
+        self.collision_policy = Some(collision_policy);
+        self
+    }
+
+    /// Attach a custom metadata value to the uploaded file. Keys may only contain ascii letters,
+    /// digits, `-` and `_`
+    pub fn metadata<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Result<Self, AssError> {
+        let key = key.into();
+        validate_metadata_key(&key)?;
+        self.metadata.insert(key, value.into());
+        Ok(self)
+    }
+
+    /// Sanitize the file name before uploading. If the name changes, the original name is kept
+    /// in the `original_filename` metadata value. Only used for file uploads
+    pub fn sanitize_filename(mut self, sanitizer: FilenameSanitizer) -> Self {
+        self.sanitizer = Some(sanitizer);
+        self
+    }
+
+    /// Add an extra header to the upload request. Headers covered by the typed options or set
+    /// by the client are rejected
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, AssError> {
+        let header_name = HeaderName::from_bytes(name.as_bytes())?;
+        if RESERVED_HEADERS.contains(&header_name.as_str()) {
+            return Err(AssError::reserved_header(name.to_string()));
+        }
+        self.headers.push((header_name, value.parse()?));
+        Ok(self)
+    }
+
+    /// Fetch the acl for the upload, if any
+    #[cfg(feature = "local")]
+    pub(crate) fn get_acl(&self) -> Option<Acl> {
+        self.acl
+    }
+
+    /// Fetch the content type for the upload, if any
+    #[cfg(feature = "local")]
+    pub(crate) fn get_content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Fetch the metadata for the upload
+    #[cfg(feature = "local")]
+    pub(crate) fn get_metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// Fetch the collision policy for the upload, if any
+    pub(crate) fn get_collision_policy(&self) -> Option<CollisionPolicy> {
+        self.collision_policy
+    }
+
+    /// Fetch the file name sanitizer for the upload, if any
+    pub(crate) fn get_sanitizer(&self) -> Option<&FilenameSanitizer> {
+        self.sanitizer.as_ref()
+    }
+
+    /// Build the headers to send with the upload request
+    pub(crate) fn to_headers(&self) -> Result<HeaderMap, AssError> {
+        let mut headers = HeaderMap::new();
+        if let Some(cache_control) = &self.cache_control {
+            headers.insert("Cache-Control", cache_control.to_string().parse()?);
+        }
+        if let Some(content_disposition) = &self.content_disposition {
+            headers.insert(
+                "Content-Disposition",
+                content_disposition.to_string().parse()?,
+            );
+        }
+        if let Some(acl) = self.acl {
+            headers.insert(ACL_HEADER, acl.to_string().parse()?);
+        }
+        if let Some(collision_policy) = self.collision_policy {
+            let overwrite = collision_policy == CollisionPolicy::Overwrite;
+            headers.insert(OVERWRITE_HEADER, overwrite.to_string().parse()?);
+        }
+        for (name, value) in &self.headers {
+            headers.append(name, value.clone());
+        }
+        Ok(headers)
+    }
+
+    /// Build the multipart form holding the uploaded content and its metadata
+    pub(crate) fn to_form(&self, content: Vec<u8>) -> Result<Form, AssError> {
+        let part = Part::stream(content);
+        let part = match &self.content_type {
+            Some(content_type) => part.mime_str(content_type)?,
+            None => part,
+        };
+        let form = Form::new().part("file", part);
+        if self.metadata.is_empty() {
+            Ok(form)
+        } else {
+            Ok(form.text("metadata", serde_json::to_string(&self.metadata)?))
+        }
+    }
+}
+
+/// Check that a metadata key is non-empty and only contains ascii letters, digits, `-` and `_`
+pub(crate) fn validate_metadata_key(key: &str) -> Result<(), AssError> {
+    let valid = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(AssError::invalid_metadata_key(key.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Acl, AssErrorKind, CacheControl, ContentDisposition, UploadOptions};
+    use std::time::Duration;
+
+    #[test]
+    fn upload_options_headers() {
+        let options = UploadOptions::new()
+            .acl(Acl::Private)
+            .overwrite(false)
+            .cache_control(CacheControl::max_age(Duration::from_secs(234)))
+            .content_disposition(ContentDisposition::Inline)
+            .header("x-custom", "value")
+            .expect("Could not add header");
+
+        let headers = options.to_headers().expect("Could not get headers");
+        assert_eq!(headers.keys_len(), 5);
+        assert_eq!(headers.get("cache-control").unwrap(), &"max-age=234");
+        assert_eq!(headers.get("content-disposition").unwrap(), &"inline");
+        assert_eq!(headers.get("x-ass-acl").unwrap(), &"private");
+        assert_eq!(headers.get("x-ass-overwrite").unwrap(), &"false");
+        assert_eq!(headers.get("x-custom").unwrap(), &"value");
+    }
+
+    #[test]
+    fn upload_options_rejects_invalid_headers() {
+        match UploadOptions::new().header("Cache-Control", "max-age=1") {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::ReservedHeader(_))),
+            _ => panic!("Reserved header should be rejected"),
+        }
+        match UploadOptions::new().header("x-custom", "line\nbreak") {
+            Err(e) => assert_eq!(e.kind, AssErrorKind::ReqwestError),
+            _ => panic!("Invalid header value should be rejected"),
+        }
+        match UploadOptions::new().metadata("article id", "2") {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidMetadataKey(_))),
+            _ => panic!("Invalid metadata key should be rejected"),
+        }
+        match UploadOptions::new().content_type("not a mime type") {
+            Err(e) => assert!(matches!(e.kind, AssErrorKind::InvalidContentType(_))),
+            _ => panic!("Invalid content type should be rejected"),
+        }
+    }
+}